@@ -1,49 +1,98 @@
-use super::datastructure::{Cons, Item};
+use super::datastructure::{Cons, Item, Number};
+use super::lexer::Position;
 use super::parser::ast::{self, AST};
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests;
 
 pub fn convert(ast: AST) -> Vec<Item> {
-    let expression_val = convert_compound(*ast.root);
+    convert_located(ast)
+        .into_iter()
+        .map(|(item, _)| item)
+        .collect()
+}
 
-    if let Item::Cons(result) = expression_val {
-        result.into()
-    } else {
-        vec![expression_val]
+/// Like `convert`, but also returns the position of every expression in the
+/// source code.
+pub fn convert_located(ast: AST) -> Vec<(Item, Option<Position>)> {
+    match convert_compound(*ast.root) {
+        Item::Cons(result) => {
+            let positions = result.element_positions().to_vec();
+            Vec::from(result).into_iter().zip(positions).collect()
+        }
+        item => vec![(item, None)],
     }
 }
 
+/// Converts the expressions of the compound into a list, which keeps the
+/// position of every element.
 fn convert_compound(compound: ast::Compound) -> Item {
-    match compound {
-        ast::Compound::Some(e, c) => {
-            let car = convert_expression(e);
-            let cdr = convert_compound(*c);
-            Item::Cons(Cons::new(car, cdr))
-        }
-        ast::Compound::None => Item::None,
+    let mut items = Vec::new();
+    let mut positions = Vec::new();
+    let mut compound = compound;
+    while let ast::Compound::Some(e, c) = compound {
+        positions.push(expression_position(&e));
+        items.push(convert_expression(e));
+        compound = *c;
+    }
+    if items.is_empty() {
+        Item::None
+    } else {
+        Item::Cons(Cons::from(items).with_element_positions(positions))
+    }
+}
+
+/// Returns the position of the expression, where a quoted expression is
+/// located at the expression it quotes.
+fn expression_position(expression: &ast::Expression) -> Option<Position> {
+    match expression {
+        ast::Expression::QuoteExpression(e)
+        | ast::Expression::QuasiquoteExpression(e)
+        | ast::Expression::UnquoteExpression(e)
+        | ast::Expression::UnquoteSplicingExpression(e) => expression_position(e),
+        ast::Expression::List(l) => Some(l.position()),
+        ast::Expression::Name(p, _) => Some(*p),
+        ast::Expression::Primitive(p, _) => Some(*p),
     }
 }
 
 fn convert_expression(expression: ast::Expression) -> Item {
     match expression {
-        ast::Expression::QuoteExpression(e) => {
-            let quote = Item::Name(String::from("quote"));
-            let content = convert_expression(*e);
-            let wrap = Item::Cons(Cons::new(content, Item::None));
-            Item::Cons(Cons::new(quote, wrap))
+        ast::Expression::QuoteExpression(e) => wrap("quote", *e),
+        ast::Expression::QuasiquoteExpression(e) => wrap("quasiquote", *e),
+        ast::Expression::UnquoteExpression(e) => wrap("unquote", *e),
+        ast::Expression::UnquoteSplicingExpression(e) => wrap("unquote-splicing", *e),
+        ast::Expression::List(l) => {
+            let position = l.position();
+            match convert_compound(*l.content) {
+                Item::Cons(list) => Item::Cons(list.with_position(position)),
+                item => item,
+            }
         }
-        ast::Expression::List(l) => convert_compound(*l.content),
         ast::Expression::Name(_, n) => Item::Name(n),
         ast::Expression::Primitive(_, l) => convert_primitive(l),
     }
 }
 
+/// Converts the expression, wrapped in a list with the name, e.g. `'x`
+/// becomes `(quote x)`.
+fn wrap(name: &str, expression: ast::Expression) -> Item {
+    let name = Item::Name(String::from(name));
+    let content = convert_expression(expression);
+    let wrap = Item::Cons(Cons::new(content, Item::None));
+    Item::Cons(Cons::new(name, wrap))
+}
+
 fn convert_primitive(primitive: ast::Literal) -> Item {
     match primitive {
-        ast::Literal::Number(n) => Item::Number(n),
+        ast::Literal::Number(n) => Item::Number(Number::Integer(n)),
+        ast::Literal::BigInteger(n) => Item::Number(Number::from(n)),
+        ast::Literal::Rational(n, d) => Item::Number(Number::rational(n, d)),
+        ast::Literal::Real(r) => Item::Number(Number::Real(r)),
         ast::Literal::Boolean(b) => Item::Boolean(b),
         ast::Literal::String(s) => Item::String(s),
+        ast::Literal::Char(c) => Item::Char(c),
         ast::Literal::None => panic!("This literal type is not yet supported!"),
     }
 }
@@ -4,7 +4,34 @@ use super::*;
 
 #[test]
 fn convert_number() {
-    assert_eq!(Item::Number(5), convert_primitive(ast::Literal::Number(5)));
+    assert_eq!(
+        Item::Number(Number::Integer(5)),
+        convert_primitive(ast::Literal::Number(5))
+    );
+}
+
+#[test]
+fn convert_rational() {
+    assert_eq!(
+        Item::Number(Number::rational(3, 4)),
+        convert_primitive(ast::Literal::Rational(3.into(), 4.into()))
+    );
+}
+
+#[test]
+fn convert_rational_to_integer() {
+    assert_eq!(
+        Item::Number(Number::Integer(2)),
+        convert_primitive(ast::Literal::Rational(4.into(), 2.into()))
+    );
+}
+
+#[test]
+fn convert_real() {
+    assert_eq!(
+        Item::Number(Number::Real(1.5)),
+        convert_primitive(ast::Literal::Real(1.5))
+    );
 }
 
 #[test]
@@ -43,7 +70,7 @@ fn convert_name() {
 #[test]
 fn convert_literal() {
     assert_eq!(
-        Item::Number(123),
+        Item::Number(Number::Integer(123)),
         convert_expression(ast::Expression::Primitive(
             ast::Position::at(1, 0),
             ast::Literal::Number(123)
@@ -91,7 +118,7 @@ fn convert_compound_some() {
     assert_eq!(
         Item::Cons(Cons::new(
             Item::Name(String::from("test")),
-            Item::Cons(Cons::new(Item::Number(123), Item::None))
+            Item::Cons(Cons::new(Item::Number(Number::Integer(123)), Item::None))
         )),
         convert_compound(ast::Compound::Some(
             ast::Expression::Name(ast::Position::at(1, 0), String::from("test")),
@@ -102,3 +129,33 @@ fn convert_compound_some() {
         ))
     );
 }
+
+#[test]
+fn convert_list_keeps_position() {
+    let list = ast::List::parse(
+        &mut [
+            Symbol::LParen(ast::Position::at(2, 4)),
+            Symbol::Name(ast::Position::at(2, 5), String::from("test")),
+            Symbol::RParen(ast::Position::at(2, 9)),
+        ]
+        .iter()
+        .peekable(),
+    );
+    match convert_expression(ast::Expression::List(list.unwrap())) {
+        Item::Cons(list) => assert_eq!(Some(ast::Position::at(2, 4)), list.position()),
+        item => panic!("Expected a list, found: {}", item),
+    }
+}
+
+#[test]
+fn convert_unquote_splicing_expression() {
+    assert_eq!(
+        Item::Cons(Cons::new(
+            Item::Name(String::from("unquote-splicing")),
+            Item::Cons(Cons::new(Item::Name(String::from("test")), Item::None))
+        )),
+        convert_expression(ast::Expression::UnquoteSplicingExpression(Box::new(
+            ast::Expression::Name(ast::Position::at(1, 2), String::from("test"))
+        )))
+    );
+}
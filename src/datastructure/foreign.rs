@@ -0,0 +1,98 @@
+use std::any::{self, Any};
+use std::fmt;
+use std::rc::Rc;
+
+/// An opaque value of the host application, e.g. a database handle.
+///
+/// Scripts can pass foreign values around, but only native functions can
+/// look inside them, by downcasting them to their Rust type. Two foreign
+/// values are equal if they are the same object.
+///
+/// # Example
+///
+/// ```
+/// use minilisp::datastructure::{Foreign, Item};
+///
+/// struct Handle(u32);
+///
+/// let item = Item::Foreign(Foreign::new(Handle(7)));
+/// if let Item::Foreign(foreign) = &item {
+///     assert_eq!(foreign.downcast_ref::<Handle>().map(|h| h.0), Some(7));
+///     assert!(foreign.downcast_ref::<String>().is_none());
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Foreign {
+    value: Rc<dyn Any>,
+    type_name: &'static str,
+}
+
+impl Foreign {
+    pub fn new<T>(value: T) -> Foreign
+    where
+        T: Any,
+    {
+        Foreign::from_rc(Rc::new(value))
+    }
+
+    /// Wraps a value, which is shared with the host.
+    pub fn from_rc<T>(value: Rc<T>) -> Foreign
+    where
+        T: Any,
+    {
+        Foreign {
+            value,
+            type_name: any::type_name::<T>(),
+        }
+    }
+
+    /// Returns the name of the Rust type of the value.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    pub fn is<T>(&self) -> bool
+    where
+        T: Any,
+    {
+        self.value.is::<T>()
+    }
+
+    /// Returns a reference to the value, if it is of type `T`.
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: Any,
+    {
+        self.value.downcast_ref()
+    }
+
+    /// Returns a shared pointer to the value, if it is of type `T`.
+    pub fn downcast<T>(&self) -> Option<Rc<T>>
+    where
+        T: Any,
+    {
+        self.value.clone().downcast().ok()
+    }
+
+    fn address(&self) -> *const () {
+        Rc::as_ptr(&self.value) as *const ()
+    }
+}
+
+impl PartialEq for Foreign {
+    fn eq(&self, other: &Self) -> bool {
+        self.address() == other.address()
+    }
+}
+
+impl fmt::Debug for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Foreign({}@{:p})", self.type_name, self.address())
+    }
+}
+
+impl fmt::Display for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#<{}@{:p}>", self.type_name, self.address())
+    }
+}
@@ -1,20 +1,33 @@
+use super::lexer::{char_name, is_plain_name, Position};
 use super::stdlib::error;
 use super::vm::Machine;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::default::Default;
 use std::fmt;
 use std::rc::Rc;
 use std::slice::Iter;
 
-pub type Number = i64;
+mod foreign;
+mod number;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(test)]
+mod tests;
+
+pub use foreign::Foreign;
+pub use number::Number;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Item {
     Number(Number),
     String(String),
+    Char(char),
     Boolean(bool),
     Name(String),
     Cons(Cons),
+    /// An opaque value of the host application.
+    Foreign(Foreign),
     None,
 }
 
@@ -22,7 +35,8 @@ impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Item::Number(num) => write!(f, "{}", num),
-            Item::String(s) => write!(f, "\"{}\"", s),
+            Item::String(s) => write_string(f, s),
+            Item::Char(c) => write_char(f, *c),
             Item::Boolean(b) => {
                 if *b {
                     write!(f, "#t")
@@ -30,21 +44,70 @@ impl fmt::Display for Item {
                     write!(f, "#f")
                 }
             }
-            Item::Name(n) => write!(f, "{}", n),
+            Item::Name(n) => write_name(f, n),
             Item::Cons(c) => write!(f, "{}", c),
+            Item::Foreign(o) => write!(f, "{}", o),
             Item::None => write!(f, "()"),
         }
     }
 }
 
+/// Writes the string as a string literal, escaping the characters which
+/// cannot be written as they are, so it reads back as the same string.
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            '\r' => write!(f, "\\r")?,
+            '\0' => write!(f, "\\0")?,
+            c if c.is_control() => write!(f, "\\x{:x};", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Writes the name, between bars if it would not read back as the same name
+/// otherwise, e.g. a name renamed by a macro like `|tmp,2|`.
+fn write_name(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    if is_plain_name(name) {
+        return write!(f, "{}", name);
+    }
+    write!(f, "|")?;
+    for c in name.chars() {
+        match c {
+            '|' => write!(f, "\\|")?,
+            '\\' => write!(f, "\\\\")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "|")
+}
+
+/// Writes the character as a character literal, using its name if it has
+/// one, and its code point if it is a control character.
+fn write_char(f: &mut fmt::Formatter<'_>, c: char) -> fmt::Result {
+    match char_name(c) {
+        Some(name) => write!(f, "#\\{}", name),
+        None if c.is_control() => write!(f, "#\\x{:x}", c as u32),
+        None => write!(f, "#\\{}", c),
+    }
+}
+
 impl From<Item> for bool {
     fn from(item: Item) -> Self {
         match item {
-            Item::Number(num) => num != 0,
+            Item::Number(num) => !num.is_zero(),
             Item::String(s) => !s.is_empty(),
+            Item::Char(_) => true,
             Item::Boolean(b) => b,
             Item::Name(_) => true,
             Item::Cons(c) => !c.is_empty(),
+            Item::Foreign(_) => true,
             Item::None => false,
         }
     }
@@ -52,10 +115,15 @@ impl From<Item> for bool {
 
 pub type ConsElement = Item;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Cons {
     data: Vec<ConsElement>,
     is_null_terminated: bool,
+    /// The position of the list in the source code, if it is known.
+    position: Option<Position>,
+    /// The positions of the elements in the source code, which is empty if
+    /// they are unknown.
+    positions: Vec<Option<Position>>,
 }
 
 impl Default for Cons {
@@ -63,35 +131,80 @@ impl Default for Cons {
         Cons {
             data: vec![ConsElement::None],
             is_null_terminated: true,
+            position: None,
+            positions: Vec::new(),
         }
     }
 }
 
+/// Two lists are equal if their elements are equal, regardless of where
+/// they are located in the source code.
+impl PartialEq for Cons {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data && self.is_null_terminated == other.is_null_terminated
+    }
+}
+
 impl Cons {
     pub fn new(car: ConsElement, cdr: ConsElement) -> Cons {
         let mut data = vec![car];
-        let is_null_terminated;
-        match cdr {
+        let mut positions = Vec::new();
+        let is_null_terminated = match cdr {
             ConsElement::Cons(cons) => {
                 data.extend(cons.data.iter().cloned());
-                is_null_terminated = cons.is_null_terminated;
-            }
-            ConsElement::None => {
-                is_null_terminated = true;
+                if !cons.positions.is_empty() {
+                    positions.push(None);
+                    positions.extend(cons.positions);
+                }
+                cons.is_null_terminated
             }
+            ConsElement::None => true,
             _ => {
                 data.push(cdr);
-                is_null_terminated = false;
+                false
             }
-        }
+        };
 
         Cons {
             data,
             is_null_terminated,
+            position: None,
+            positions,
+        }
+    }
+
+    /// Returns the list located at the given position in the source code.
+    pub fn with_position(self, position: Position) -> Cons {
+        Cons {
+            position: Some(position),
+            ..self
         }
     }
 
-    pub fn iter(&self) -> Iter<ConsElement> {
+    /// Returns the position of the list in the source code, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Returns the list, whose elements are located at the given positions
+    /// in the source code.
+    pub fn with_element_positions(self, positions: Vec<Option<Position>>) -> Cons {
+        Cons { positions, ..self }
+    }
+
+    /// Returns the positions of the elements in the source code, which is
+    /// empty if they are unknown.
+    pub fn element_positions(&self) -> &[Option<Position>] {
+        &self.positions
+    }
+
+    /// Returns the position of the element at the index in the source code,
+    /// if it is known.
+    pub fn position_of(&self, index: usize) -> Option<Position> {
+        self.positions.get(index).copied().flatten()
+    }
+
+    pub fn iter(&self) -> Iter<'_, ConsElement> {
         self.data.iter()
     }
 
@@ -100,68 +213,109 @@ impl Cons {
     }
 
     pub fn cdr(&self) -> ConsElement {
-        match self.data.len() {
-            2 if !self.is_null_terminated => self.data[1].clone(),
-            1 => ConsElement::None,
-            0 => panic!("A Cons should never be empty! Contact your vendor. this is a bug"),
-            _ => ConsElement::Cons(Cons {
-                data: (&self.data[1..]).to_vec(),
-                is_null_terminated: self.is_null_terminated,
-            }),
+        match self.nth_cdr(1) {
+            Some(cdr) => cdr,
+            None => panic!("A Cons should never be empty! Contact your vendor. this is a bug"),
         }
     }
 
-    pub fn cddr(&self) -> ConsElement {
-        match self.data.len() {
-            3 if !self.is_null_terminated => self.data[2].clone(),
-            2 => ConsElement::None,
-            1 => panic!("Not enough elements!"), // TODO This should be handled more gracefully
-            0 => panic!("A Cons should never be empty! Contact your vendor. this is a bug"),
-            _ => ConsElement::Cons(Cons {
-                data: (&self.data[2..]).to_vec(),
+    /// Returns the elements of the list, without the final `cdr` of a dotted
+    /// list.
+    pub fn elements(&self) -> &[ConsElement] {
+        if self.is_null_terminated {
+            &self.data
+        } else {
+            &self.data[..self.data.len() - 1]
+        }
+    }
+
+    /// Returns the element at the index, or `None` if the list is too short.
+    pub fn get(&self, index: usize) -> Option<&ConsElement> {
+        self.elements().get(index)
+    }
+
+    /// Returns the rest of the list after the first `n` elements, like
+    /// applying `cdr` `n` times, or `None` if the list is too short.
+    pub fn nth_cdr(&self, n: usize) -> Option<ConsElement> {
+        let count = self.elements().len();
+        if n == 0 {
+            Some(ConsElement::Cons(self.clone()))
+        } else if n < count {
+            Some(ConsElement::Cons(Cons {
+                data: self.data[n..].to_vec(),
                 is_null_terminated: self.is_null_terminated,
-            }),
+                position: None,
+                positions: self.positions.get(n..).unwrap_or_default().to_vec(),
+            }))
+        } else if n == count && self.is_null_terminated {
+            Some(ConsElement::None)
+        } else if n == count {
+            self.data.last().cloned()
+        } else {
+            None
         }
     }
 
+    pub fn try_cddr(&self) -> Option<ConsElement> {
+        self.nth_cdr(2)
+    }
+
+    pub fn try_cadr(&self) -> Option<&ConsElement> {
+        self.get(1)
+    }
+
+    pub fn try_caddr(&self) -> Option<&ConsElement> {
+        self.get(2)
+    }
+
+    pub fn try_cadddr(&self) -> Option<&ConsElement> {
+        self.get(3)
+    }
+
+    /// Like `try_cddr`, but panics if the list is too short.
+    #[deprecated(note = "panics on short lists, use `try_cddr` or `nth_cdr` instead")]
+    pub fn cddr(&self) -> ConsElement {
+        self.try_cddr().expect("Not enough elements!")
+    }
+
+    /// Returns the elements after the first two, and panics if the list is
+    /// too short.
+    #[deprecated(note = "panics on short lists, use `elements().get(2..)` instead")]
     pub fn cddr_list(&self) -> Vec<Item> {
-        match self.data.len() {
-            3 if !self.is_null_terminated => vec![self.data[2].clone()],
-            2 => Vec::new(),
-            1 => panic!("Not enough elements!"), // TODO This should be handled more gracefully
-            0 => panic!("A Cons should never be empty! Contact your vendor. this is a bug"),
-            _ => (&self.data[2..]).to_vec(),
+        match self.elements().get(2..) {
+            Some(rest) => rest.to_vec(),
+            None => panic!("Not enough elements!"),
         }
     }
 
+    /// Like `try_cadr`, but panics if the list is too short.
+    #[deprecated(note = "panics on short lists, use `try_cadr` or `get` instead")]
     pub fn cadr(&self) -> &ConsElement {
-        if self.len() > 2 || (self.len() > 1 && self.is_null_terminated) {
-            &self.data[1]
-        } else {
-            panic!("Not enough elements!"); // TODO This should be handled more gracefully
-        }
+        self.try_cadr().expect("Not enough elements!")
     }
 
+    /// Like `try_caddr`, but panics if the list is too short.
+    #[deprecated(note = "panics on short lists, use `try_caddr` or `get` instead")]
     pub fn caddr(&self) -> &ConsElement {
-        if self.len() > 3 || (self.len() > 2 && self.is_null_terminated) {
-            &self.data[2]
-        } else {
-            panic!("Not enough elements!"); // TODO This should be handled more gracefully
-        }
+        self.try_caddr().expect("Not enough elements!")
     }
 
+    /// Like `try_cadddr`, but panics if the list is too short.
+    #[deprecated(note = "panics on short lists, use `try_cadddr` or `get` instead")]
     pub fn cadddr(&self) -> &ConsElement {
-        if self.len() > 4 || (self.len() > 3 && self.is_null_terminated) {
-            &self.data[3]
-        } else {
-            panic!("Not enough elements!"); // TODO This should be handled more gracefully
-        }
+        self.try_cadddr().expect("Not enough elements!")
     }
 
     pub fn len(&self) -> usize {
         self.data.len()
     }
 
+    /// Returns true if the last `cdr` of the list is the empty list, i.e. the
+    /// list is not a dotted list like `(a . b)`.
+    pub fn is_null_terminated(&self) -> bool {
+        self.is_null_terminated
+    }
+
     pub fn is_empty(&self) -> bool {
         self.data.len() == 1 && self.data[0] == ConsElement::None
     }
@@ -173,6 +327,8 @@ impl Cons {
         Cons {
             data: self.data.iter().map(f).collect(),
             is_null_terminated: self.is_null_terminated,
+            position: self.position,
+            positions: self.positions,
         }
     }
 }
@@ -211,6 +367,8 @@ impl From<Vec<Item>> for Cons {
         Cons {
             data: list,
             is_null_terminated: true,
+            position: None,
+            positions: Vec::new(),
         }
     }
 }
@@ -228,7 +386,9 @@ pub enum Parameters {
 
 #[derive(Clone)]
 pub enum EnvItem {
-    Function(String, EnvItemFunctionWrapped, Parameters),
+    /// A callable value. The last field is the scope captured when the
+    /// function was created, which is `None` for native functions.
+    Function(String, EnvItemFunctionWrapped, Parameters, Option<Scope>),
     Data(Item),
     VariableBinding(Vec<EnvItem>),
     None,
@@ -237,7 +397,7 @@ pub enum EnvItem {
 impl fmt::Debug for EnvItem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EnvItem::Function(name, func, _) => write!(f, "<{}@{:p}>", name, func),
+            EnvItem::Function(name, func, _, _) => write!(f, "<{}@{:p}>", name, func),
             EnvItem::Data(d) => write!(f, "{:?}", d),
             EnvItem::VariableBinding(v) => write!(f, "{:?}", v),
             EnvItem::None => write!(f, "None"),
@@ -248,7 +408,7 @@ impl fmt::Debug for EnvItem {
 impl fmt::Display for EnvItem {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            EnvItem::Function(name, _, _) => write!(f, "{}", name),
+            EnvItem::Function(name, _, _, _) => write!(f, "{}", name),
             EnvItem::Data(d) => write!(f, "{}", d),
             EnvItem::VariableBinding(v) => write!(
                 f,
@@ -263,14 +423,28 @@ impl fmt::Display for EnvItem {
     }
 }
 
+/// Converts a value of the environment into data. Functions are represented
+/// by their name.
+impl From<EnvItem> for Item {
+    fn from(item: EnvItem) -> Self {
+        match item {
+            EnvItem::Function(name, _, _, _) => Item::Name(name),
+            EnvItem::Data(item) => item,
+            EnvItem::VariableBinding(items) if items.is_empty() => Item::None,
+            EnvItem::VariableBinding(items) => Item::Cons(Cons::from(
+                items.into_iter().map(Item::from).collect::<Vec<_>>(),
+            )),
+            EnvItem::None => Item::None,
+        }
+    }
+}
+
 impl PartialEq for EnvItem {
     fn eq(&self, other: &Self) -> bool {
         match self {
-            EnvItem::Function(_, f, _) => {
-                if let EnvItem::Function(_, o, _) = other {
-                    let left: *const EnvItemFunction = f.as_ref();
-                    let right: *const EnvItemFunction = o.as_ref();
-                    left == right
+            EnvItem::Function(_, f, _, _) => {
+                if let EnvItem::Function(_, o, _, _) = other {
+                    Rc::ptr_eq(f, o)
                 } else {
                     false
                 }
@@ -294,22 +468,85 @@ impl PartialEq for EnvItem {
     }
 }
 
+/// A single layer of bindings, linked to the layer it was created within.
+pub struct Frame {
+    variables: HashMap<String, EnvItem>,
+    parent: Option<Scope>,
+}
+
+/// A shared handle to a `Frame`. Functions keep a `Scope` alive for as long
+/// as they can be called, which is what makes closures possible.
+///
+/// A function bound within the frame it keeps alive, like the functions of a
+/// `letrec` or of a recursive local `def`, forms a reference cycle. Such a
+/// frame is never freed, even after the function can no longer be called.
+pub type Scope = Rc<RefCell<Frame>>;
+
+impl Frame {
+    /// Creates a new empty scope, which falls back to `parent` on lookups.
+    pub fn new_scope(parent: Option<Scope>) -> Scope {
+        Rc::new(RefCell::new(Frame {
+            variables: HashMap::new(),
+            parent,
+        }))
+    }
+
+    /// Looks up the key in this frame, and then in each of the parents
+    /// until it is found. If no item is found, None is returned.
+    pub fn lookup(&self, key: &str) -> EnvItem {
+        match self.variables.get(key) {
+            Some(item) => item.clone(),
+            None => match &self.parent {
+                Some(parent) => parent.borrow().lookup(key),
+                None => EnvItem::None,
+            },
+        }
+    }
+
+    /// Assigns a value to the key in this frame, shadowing any binding in
+    /// the parents.
+    pub fn assign(&mut self, key: String, value: EnvItem) -> Option<EnvItem> {
+        self.variables.insert(key, value)
+    }
+
+    /// Replaces the value of the nearest binding of the key, in this frame
+    /// or one of the parents. Returns false if the key is not bound.
+    pub fn set(&mut self, key: &str, value: EnvItem) -> bool {
+        match self.variables.get_mut(key) {
+            Some(item) => {
+                *item = value;
+                true
+            }
+            None => match &self.parent {
+                Some(parent) => parent.borrow_mut().set(key, value),
+                None => false,
+            },
+        }
+    }
+}
+
+/// The environment is a stack of scopes, one for each stack frame of the
+/// machine. The bottom scope holds the global definitions, and every other
+/// scope is linked to the scope it was created within, so names are
+/// resolved lexically rather than by walking the stack.
 pub struct Environment {
-    variables: Vec<HashMap<String, EnvItem>>,
+    variables: Vec<Scope>,
 }
 
 impl Default for Environment {
     fn default() -> Self {
         Environment {
-            variables: vec![HashMap::new()],
+            variables: vec![Frame::new_scope(None)],
         }
     }
 }
 
 impl Environment {
-    /// Pushes a new environment layer on top of the environment stack
+    /// Pushes a new environment layer on top of the environment stack.
+    /// The new layer is linked to the current top layer.
     pub fn push(&mut self) {
-        self.variables.push(HashMap::new());
+        let scope = Frame::new_scope(Some(self.current()));
+        self.variables.push(scope);
     }
 
     /// Pops the top layer of the environment stack
@@ -319,19 +556,71 @@ impl Environment {
         }
     }
 
+    /// Pops every layer except for the global one.
+    pub fn clear(&mut self) {
+        self.variables.truncate(1);
+    }
+
+    /// Pops every layer except for the global one, and returns them, so they
+    /// can be restored by `resume`.
+    pub fn suspend(&mut self) -> Vec<Scope> {
+        self.variables.split_off(1)
+    }
+
+    /// Restores the layers returned by `suspend`, replacing every layer
+    /// except for the global one.
+    pub fn resume(&mut self, layers: Vec<Scope>) {
+        self.variables.truncate(1);
+        self.variables.extend(layers);
+    }
+
+    /// Returns the scope of the bottom layer, which holds the global
+    /// definitions.
+    pub fn global(&self) -> Scope {
+        match self.variables.first() {
+            Some(scope) => scope.clone(),
+            None => panic!(
+                "The global scope should never be popped! Contact your vendor. this is a bug"
+            ),
+        }
+    }
+
+    /// Returns the scope of the top layer of the environment stack.
+    pub fn current(&self) -> Scope {
+        match self.variables.last() {
+            Some(scope) => scope.clone(),
+            None => panic!(
+                "The global scope should never be popped! Contact your vendor. this is a bug"
+            ),
+        }
+    }
+
+    /// Replaces the top layer of the environment stack with a new layer
+    /// linked to the given scope. This is used when calling a function,
+    /// so the body sees the scope the function was created within,
+    /// rather than the scope of the caller.
+    pub fn enter(&mut self, scope: Scope) {
+        let scope = Frame::new_scope(Some(scope));
+        if self.variables.len() > 1 {
+            self.variables.pop();
+        }
+        self.variables.push(scope);
+    }
+
     /// Used for looking up a named value within the environment.
-    /// Starting from the top layer, which shadows the lower layers, and moves
-    /// down the stack. If no item is found, None is returned.
+    /// Starting from the top layer, and following the chain of scopes it
+    /// was created within. If no item is found, None is returned.
     pub fn lookup(&self, key: &str) -> EnvItem {
-        for var in self.variables.iter().rev() {
-            if var.contains_key(key) {
-                return match var.get(key) {
-                    Some(item) => item.clone(),
-                    None => EnvItem::None,
-                };
-            }
+        self.current().borrow().lookup(key)
+    }
+
+    /// Looks up a named value in the bottom layer of the environment stack.
+    /// If no item is found, None is returned.
+    pub fn lookup_global(&self, key: &str) -> EnvItem {
+        match self.variables.first() {
+            Some(scope) => scope.borrow().lookup(key),
+            None => EnvItem::None,
         }
-        EnvItem::None
     }
 
     /// This assigns a value to the key in the top layer of the environment
@@ -340,12 +629,13 @@ impl Environment {
     where
         T: Into<String>,
     {
-        let key = key.into();
-        if let Some(var) = self.variables.last_mut() {
-            var.insert(key, value)
-        } else {
-            None
-        }
+        self.current().borrow_mut().assign(key.into(), value)
+    }
+
+    /// Replaces the value of the nearest binding of the key, starting from
+    /// the top layer. Returns false if the key is not bound.
+    pub fn set(&mut self, key: &str, value: EnvItem) -> bool {
+        self.current().borrow_mut().set(key, value)
     }
 
     /// This assigns a value to the key in the bottom layer of the environment
@@ -355,11 +645,9 @@ impl Environment {
     where
         T: Into<String>,
     {
-        let key = key.into();
-        if let Some(var) = self.variables.first_mut() {
-            var.insert(key, value)
-        } else {
-            None
+        match self.variables.first() {
+            Some(scope) => scope.borrow_mut().assign(key.into(), value),
+            None => None,
         }
     }
 }
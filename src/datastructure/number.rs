@@ -0,0 +1,315 @@
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Rem, Sub, SubAssign};
+
+/// Representation of the numbers of the language.
+///
+/// The numbers follow a simplified version of the Scheme numeric tower.
+/// Integers and rationals are exact, while reals are inexact.
+/// When two numbers of different kinds are combined, both are promoted to
+/// the most general of the two kinds, in the order `Integer`, `BigInteger`,
+/// `Rational`, `Real`. Exact results are always normalized, so a rational
+/// with a denominator of one becomes an integer, and a big integer that
+/// fits in an `i64` becomes an `Integer`.
+///
+/// Arithmetic on integers never overflows, as the result is promoted to a
+/// `BigInteger` instead.
+#[derive(Debug, Clone)]
+pub enum Number {
+    Integer(i64),
+    BigInteger(BigInt),
+    Rational(BigRational),
+    Real(f64),
+}
+
+/// Two numbers promoted to the same kind.
+enum Pair {
+    Integer(i64, i64),
+    BigInteger(BigInt, BigInt),
+    Rational(BigRational, BigRational),
+    Real(f64, f64),
+}
+
+impl Number {
+    /// Generates the exact number `numerator / denominator`.
+    ///
+    /// # Panics
+    /// Panics if the denominator is zero.
+    pub fn rational<T>(numerator: T, denominator: T) -> Number
+    where
+        T: Into<BigInt>,
+    {
+        Number::from(BigRational::new(numerator.into(), denominator.into()))
+    }
+
+    /// Returns true if the number is exact, i.e. not a `Real`.
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, Number::Real(_))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Number::Integer(i) => *i == 0,
+            Number::BigInteger(i) => i.is_zero(),
+            Number::Rational(r) => r.is_zero(),
+            Number::Real(f) => *f == 0.0,
+        }
+    }
+
+    /// Divides the numbers, returning `None` if the divisor is zero.
+    pub fn checked_div(self, other: Number) -> Option<Number> {
+        if other.is_zero() {
+            None
+        } else {
+            Some(self / other)
+        }
+    }
+
+    /// Calculates the remainder, returning `None` if the divisor is zero.
+    pub fn checked_rem(self, other: Number) -> Option<Number> {
+        if other.is_zero() {
+            None
+        } else {
+            Some(self % other)
+        }
+    }
+
+    /// Converts the number to the closest `f64`.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Integer(i) => *i as f64,
+            Number::BigInteger(i) => i.to_f64().unwrap_or(f64::NAN),
+            Number::Rational(r) => r.to_f64().unwrap_or(f64::NAN),
+            Number::Real(f) => *f,
+        }
+    }
+
+    /// Converts the number to an `i64`, if it is an integer within its
+    /// range.
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Number::Integer(i) => Some(*i),
+            Number::BigInteger(i) => i.to_i64(),
+            Number::Rational(r) if r.is_integer() => r.to_integer().to_i64(),
+            _ => None,
+        }
+    }
+
+    fn to_rational(&self) -> BigRational {
+        match self {
+            Number::Integer(i) => BigRational::from_integer(BigInt::from(*i)),
+            Number::BigInteger(i) => BigRational::from_integer(i.clone()),
+            Number::Rational(r) => r.clone(),
+            Number::Real(f) => BigRational::from_float(*f).unwrap_or_else(BigRational::zero),
+        }
+    }
+
+    fn to_big_integer(&self) -> BigInt {
+        match self {
+            Number::Integer(i) => BigInt::from(*i),
+            Number::BigInteger(i) => i.clone(),
+            Number::Rational(r) => r.to_integer(),
+            Number::Real(f) => BigInt::from_f64(*f).unwrap_or_else(BigInt::zero),
+        }
+    }
+
+    fn coerce(self, other: Number) -> Pair {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => Pair::Integer(a, b),
+            (Number::Real(a), b) => Pair::Real(a, b.to_f64()),
+            (a, Number::Real(b)) => Pair::Real(a.to_f64(), b),
+            (a @ Number::Rational(_), b) | (a, b @ Number::Rational(_)) => {
+                Pair::Rational(a.to_rational(), b.to_rational())
+            }
+            (a, b) => Pair::BigInteger(a.to_big_integer(), b.to_big_integer()),
+        }
+    }
+}
+
+impl From<i64> for Number {
+    fn from(i: i64) -> Self {
+        Number::Integer(i)
+    }
+}
+
+impl From<f64> for Number {
+    fn from(f: f64) -> Self {
+        Number::Real(f)
+    }
+}
+
+impl From<BigInt> for Number {
+    fn from(i: BigInt) -> Self {
+        match i.to_i64() {
+            Some(i) => Number::Integer(i),
+            None => Number::BigInteger(i),
+        }
+    }
+}
+
+impl From<BigRational> for Number {
+    fn from(r: BigRational) -> Self {
+        if r.is_integer() {
+            Number::from(r.to_integer())
+        } else {
+            Number::Rational(r)
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Integer(i) => write!(f, "{}", i),
+            Number::BigInteger(i) => write!(f, "{}", i),
+            Number::Rational(r) => write!(f, "{}", r),
+            // The debug representation always contains a decimal point or
+            // an exponent, which means that it is read back as a real.
+            Number::Real(r) => write!(f, "{:?}", r),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match self.clone().coerce(other.clone()) {
+            Pair::Integer(a, b) => a.partial_cmp(&b),
+            Pair::BigInteger(a, b) => a.partial_cmp(&b),
+            Pair::Rational(a, b) => a.partial_cmp(&b),
+            Pair::Real(a, b) => a.partial_cmp(&b),
+        }
+    }
+}
+
+impl Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        match self {
+            Number::Integer(i) => match i.checked_neg() {
+                Some(i) => Number::Integer(i),
+                None => Number::from(-BigInt::from(i)),
+            },
+            Number::BigInteger(i) => Number::from(-i),
+            Number::Rational(r) => Number::Rational(-r),
+            Number::Real(f) => Number::Real(-f),
+        }
+    }
+}
+
+impl Add for Number {
+    type Output = Number;
+
+    fn add(self, other: Number) -> Number {
+        match self.coerce(other) {
+            Pair::Integer(a, b) => match a.checked_add(b) {
+                Some(sum) => Number::Integer(sum),
+                None => Number::from(BigInt::from(a) + b),
+            },
+            Pair::BigInteger(a, b) => Number::from(a + b),
+            Pair::Rational(a, b) => Number::from(a + b),
+            Pair::Real(a, b) => Number::Real(a + b),
+        }
+    }
+}
+
+impl Sub for Number {
+    type Output = Number;
+
+    fn sub(self, other: Number) -> Number {
+        match self.coerce(other) {
+            Pair::Integer(a, b) => match a.checked_sub(b) {
+                Some(difference) => Number::Integer(difference),
+                None => Number::from(BigInt::from(a) - b),
+            },
+            Pair::BigInteger(a, b) => Number::from(a - b),
+            Pair::Rational(a, b) => Number::from(a - b),
+            Pair::Real(a, b) => Number::Real(a - b),
+        }
+    }
+}
+
+impl Mul for Number {
+    type Output = Number;
+
+    fn mul(self, other: Number) -> Number {
+        match self.coerce(other) {
+            Pair::Integer(a, b) => match a.checked_mul(b) {
+                Some(product) => Number::Integer(product),
+                None => Number::from(BigInt::from(a) * b),
+            },
+            Pair::BigInteger(a, b) => Number::from(a * b),
+            Pair::Rational(a, b) => Number::from(a * b),
+            Pair::Real(a, b) => Number::Real(a * b),
+        }
+    }
+}
+
+impl AddAssign for Number {
+    fn add_assign(&mut self, other: Number) {
+        *self = mem::replace(self, Number::Integer(0)) + other;
+    }
+}
+
+impl SubAssign for Number {
+    fn sub_assign(&mut self, other: Number) {
+        *self = mem::replace(self, Number::Integer(0)) - other;
+    }
+}
+
+impl MulAssign for Number {
+    fn mul_assign(&mut self, other: Number) {
+        *self = mem::replace(self, Number::Integer(0)) * other;
+    }
+}
+
+impl Div for Number {
+    type Output = Number;
+
+    /// Divides the numbers. Dividing two integers results in an integer
+    /// if the division is exact, and otherwise in a rational.
+    ///
+    /// # Panics
+    /// Panics if the divisor is an exact zero. Use `checked_div` instead,
+    /// when the divisor is not known to be non-zero.
+    fn div(self, other: Number) -> Number {
+        match self.coerce(other) {
+            Pair::Integer(a, b) => match (a.checked_rem(b), a.checked_div(b)) {
+                (Some(0), Some(quotient)) => Number::Integer(quotient),
+                _ => Number::from(BigRational::new(a.into(), b.into())),
+            },
+            Pair::BigInteger(a, b) => Number::from(BigRational::new(a, b)),
+            Pair::Rational(a, b) => Number::from(a / b),
+            Pair::Real(a, b) => Number::Real(a / b),
+        }
+    }
+}
+
+impl Rem for Number {
+    type Output = Number;
+
+    /// # Panics
+    /// Panics if the divisor is an exact zero. Use `checked_rem` instead,
+    /// when the divisor is not known to be non-zero.
+    fn rem(self, other: Number) -> Number {
+        match self.coerce(other) {
+            Pair::Integer(a, b) => match a.checked_rem(b) {
+                Some(remainder) => Number::Integer(remainder),
+                None => Number::from(BigInt::from(a) % b),
+            },
+            Pair::BigInteger(a, b) => Number::from(a % b),
+            Pair::Rational(a, b) => Number::from(a % b),
+            Pair::Real(a, b) => Number::Real(a % b),
+        }
+    }
+}
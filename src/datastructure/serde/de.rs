@@ -0,0 +1,334 @@
+use super::super::{Cons, Item, Number};
+use super::Error;
+use ::serde::de::{self, Visitor};
+use ::serde::forward_to_deserialize_any;
+use num_traits::ToPrimitive;
+use std::vec;
+
+/// A deserializer reading from an `Item`.
+pub struct Deserializer {
+    item: Item,
+}
+
+impl Deserializer {
+    pub fn new(item: Item) -> Deserializer {
+        Deserializer { item }
+    }
+}
+
+/// Returns true if every element of the list is a dotted pair, meaning that
+/// the list is read as a map, when the type is not known.
+fn is_alist(list: &Cons) -> bool {
+    list.iter().all(|item| match item {
+        Item::Cons(entry) => entry.len() == 2 && !entry.is_null_terminated(),
+        _ => false,
+    })
+}
+
+fn unexpected(item: &Item, expected: &str) -> Error {
+    Error::new(format!("Expected {}, found: {}", expected, item))
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.item {
+            Item::Number(Number::Integer(i)) => visitor.visit_i64(i),
+            Item::Number(Number::BigInteger(i)) => match (i.to_u64(), i.to_i128()) {
+                (Some(u), _) => visitor.visit_u64(u),
+                (None, Some(i)) => visitor.visit_i128(i),
+                (None, None) => Err(Error::new(format!("The integer {} is too large", i))),
+            },
+            Item::Number(n) => visitor.visit_f64(n.to_f64()),
+            Item::String(s) | Item::Name(s) => visitor.visit_string(s),
+            Item::Char(c) => visitor.visit_char(c),
+            Item::Boolean(b) => visitor.visit_bool(b),
+            Item::None => visitor.visit_unit(),
+            Item::Foreign(o) => Err(Error::new(format!(
+                "The foreign value {} cannot be deserialized",
+                o
+            ))),
+            Item::Cons(list) => {
+                if is_alist(&list) {
+                    visitor.visit_map(MapDeserializer::new(Vec::from(list)))
+                } else {
+                    visitor.visit_seq(SeqDeserializer::new(Vec::from(list)))
+                }
+            }
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.item {
+            Item::None => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.item {
+            Item::String(s) => visitor.visit_byte_buf(s.into_bytes()),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.item {
+            Item::None => visitor.visit_seq(SeqDeserializer::new(Vec::new())),
+            Item::Cons(list) => visitor.visit_seq(SeqDeserializer::new(Vec::from(list))),
+            item => Err(unexpected(&item, "a list")),
+        }
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.item {
+            Item::None => visitor.visit_map(MapDeserializer::new(Vec::new())),
+            Item::Cons(list) => visitor.visit_map(MapDeserializer::new(Vec::from(list))),
+            item => Err(unexpected(&item, "an association list")),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.item {
+            variant @ Item::Name(_) | variant @ Item::String(_) => {
+                visitor.visit_enum(EnumDeserializer {
+                    variant,
+                    value: None,
+                })
+            }
+            Item::Cons(list) => visitor.visit_enum(EnumDeserializer {
+                variant: list.car().clone(),
+                value: Some(list.cdr()),
+            }),
+            item => Err(unexpected(&item, "a variant")),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct identifier
+    }
+}
+
+struct SeqDeserializer {
+    iter: vec::IntoIter<Item>,
+}
+
+impl SeqDeserializer {
+    fn new(items: Vec<Item>) -> SeqDeserializer {
+        SeqDeserializer {
+            iter: items.into_iter(),
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(item) => seed.deserialize(Deserializer::new(item)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Reads an association list, where the `car` of each entry is the key,
+/// and the `cdr` is the value.
+struct MapDeserializer {
+    iter: vec::IntoIter<Item>,
+    value: Option<Item>,
+}
+
+impl MapDeserializer {
+    fn new(entries: Vec<Item>) -> MapDeserializer {
+        MapDeserializer {
+            iter: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some(Item::Cons(entry)) => {
+                self.value = Some(entry.cdr());
+                seed.deserialize(Deserializer::new(entry.car().clone()))
+                    .map(Some)
+            }
+            Some(item) => Err(unexpected(&item, "an entry of an association list")),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(value) => seed.deserialize(Deserializer::new(value)),
+            None => Err(Error::new("A map value was read before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumDeserializer {
+    variant: Item,
+    value: Option<Item>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, VariantDeserializer), Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(Deserializer::new(self.variant))?;
+        Ok((variant, VariantDeserializer { value: self.value }))
+    }
+}
+
+struct VariantDeserializer {
+    value: Option<Item>,
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            None | Some(Item::None) => Ok(()),
+            Some(item) => Err(unexpected(&item, "a unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(Deserializer::new(self.value.unwrap_or(Item::None)))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(
+            Deserializer::new(self.value.unwrap_or(Item::None)),
+            visitor,
+        )
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(
+            Deserializer::new(self.value.unwrap_or(Item::None)),
+            visitor,
+        )
+    }
+}
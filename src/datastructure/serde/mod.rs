@@ -0,0 +1,113 @@
+//! This module converts between `Item` and Rust types implementing
+//! `Serialize` and `Deserialize`. It is only available with the `serde`
+//! feature.
+//!
+//! # Data model
+//!
+//! | Rust                               | Lisp                          |
+//! |------------------------------------|-------------------------------|
+//! | `bool`                             | `#t`, `#f`                    |
+//! | integers and floats                | numbers                       |
+//! | `char`                             | `#\c`                         |
+//! | `String`                           | strings                       |
+//! | `()`, `None`, unit structs         | `()`                          |
+//! | `Some(x)`, newtype structs         | `x`                           |
+//! | sequences, tuples                  | `(a b c)`                     |
+//! | maps                               | `((key . value) ...)`         |
+//! | structs                            | `((field . value) ...)`       |
+//! | `E::A`                             | `A`                           |
+//! | `E::B(x)`                          | `(B . x)`                     |
+//! | `E::C(x, y)`                       | `(C x y)`                     |
+//! | `E::D { f: x }`                    | `(D (f . x))`                 |
+//!
+//! Field and variant names become names, while map keys are converted like
+//! any other value. When deserializing, names and strings are
+//! interchangeable, and a `char` can also be read from a string of one
+//! character.
+//!
+//! # Example
+//!
+//! ```
+//! use minilisp::datastructure::serde::{from_item, to_item};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct Config {
+//!     name: String,
+//!     retries: u8,
+//! }
+//!
+//! let config = Config { name: "server".into(), retries: 3 };
+//! let item = to_item(&config).unwrap();
+//! assert_eq!(format!("{}", item), "((name . \"server\") (retries . 3))");
+//! assert_eq!(from_item::<Config>(item).unwrap(), config);
+//! ```
+
+use super::Item;
+use ::serde::{Deserialize, Serialize};
+use std::fmt;
+
+mod de;
+mod ser;
+#[cfg(test)]
+mod tests;
+
+pub use de::Deserializer;
+pub use ser::Serializer;
+
+/// An error converting between an `Item` and a Rust type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    pub fn new<T>(message: T) -> Error
+    where
+        T: Into<String>,
+    {
+        Error {
+            message: message.into(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ::serde::ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+}
+
+impl ::serde::de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+}
+
+/// Converts the value into an `Item`.
+pub fn to_item<T>(value: &T) -> Result<Item, Error>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(Serializer)
+}
+
+/// Converts the `Item` into a value of type `T`.
+pub fn from_item<T>(item: Item) -> Result<T, Error>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    T::deserialize(Deserializer::new(item))
+}
@@ -0,0 +1,377 @@
+use super::super::{Cons, Item, Number};
+use super::Error;
+use ::serde::ser::{self, Serialize};
+use num_bigint::BigInt;
+
+/// A serializer producing an `Item`.
+pub struct Serializer;
+
+fn list(items: Vec<Item>) -> Item {
+    if items.is_empty() {
+        Item::None
+    } else {
+        Item::Cons(Cons::from(items))
+    }
+}
+
+fn pair(car: Item, cdr: Item) -> Item {
+    Item::Cons(Cons::new(car, cdr))
+}
+
+impl ser::Serializer for Serializer {
+    type Ok = Item;
+    type Error = Error;
+
+    type SerializeSeq = SerializeList;
+    type SerializeTuple = SerializeList;
+    type SerializeTupleStruct = SerializeList;
+    type SerializeTupleVariant = SerializeList;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeMap;
+    type SerializeStructVariant = SerializeMap;
+
+    fn serialize_bool(self, v: bool) -> Result<Item, Error> {
+        Ok(Item::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Item, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Item, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Item, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Item, Error> {
+        Ok(Item::Number(Number::Integer(v)))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Item, Error> {
+        Ok(Item::Number(Number::from(BigInt::from(v))))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Item, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Item, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Item, Error> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Item, Error> {
+        Ok(Item::Number(Number::from(BigInt::from(v))))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Item, Error> {
+        Ok(Item::Number(Number::from(BigInt::from(v))))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Item, Error> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Item, Error> {
+        Ok(Item::Number(Number::Real(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Item, Error> {
+        Ok(Item::Char(v))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Item, Error> {
+        Ok(Item::String(v.into()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Item, Error> {
+        Ok(list(
+            v.iter()
+                .map(|b| Item::Number(Number::Integer(i64::from(*b))))
+                .collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<Item, Error> {
+        Ok(Item::None)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Item, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Item, Error> {
+        Ok(Item::None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Item, Error> {
+        Ok(Item::None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Item, Error> {
+        Ok(Item::Name(variant.into()))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Item, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Item, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(pair(Item::Name(variant.into()), value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeList, Error> {
+        Ok(SerializeList {
+            variant: None,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeList, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeList, Error> {
+        Ok(SerializeList {
+            variant: Some(variant),
+            items: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: None,
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<SerializeMap, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap, Error> {
+        Ok(SerializeMap {
+            variant: Some(variant),
+            entries: Vec::with_capacity(len),
+            key: None,
+        })
+    }
+}
+
+/// Serializes sequences, tuples and tuple variants into lists.
+pub struct SerializeList {
+    variant: Option<&'static str>,
+    items: Vec<Item>,
+}
+
+impl SerializeList {
+    fn push<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.items.push(value.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Item, Error> {
+        let items = list(self.items);
+        Ok(match self.variant {
+            Some(variant) => pair(Item::Name(variant.into()), items),
+            None => items,
+        })
+    }
+}
+
+impl ser::SerializeSeq for SerializeList {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeList {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeList {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeList {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
+
+/// Serializes maps, structs and struct variants into association lists.
+pub struct SerializeMap {
+    variant: Option<&'static str>,
+    entries: Vec<Item>,
+    key: Option<Item>,
+}
+
+impl SerializeMap {
+    fn finish(self) -> Result<Item, Error> {
+        let entries = list(self.entries);
+        Ok(match self.variant {
+            Some(variant) => pair(Item::Name(variant.into()), entries),
+            None => entries,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.key = Some(key.serialize(Serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        match self.key.take() {
+            Some(key) => {
+                self.entries.push(pair(key, value.serialize(Serializer)?));
+                Ok(())
+            }
+            None => Err(Error::new("A map value was serialized before its key")),
+        }
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.entries
+            .push(pair(Item::Name(key.into()), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap {
+    type Ok = Item;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.entries
+            .push(pair(Item::Name(key.into()), value.serialize(Serializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Item, Error> {
+        self.finish()
+    }
+}
@@ -0,0 +1,120 @@
+use super::*;
+use crate::datastructure::{Cons, Number};
+use ::serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Server {
+    host: String,
+    port: u16,
+    tags: Vec<String>,
+    backup: Option<Box<Server>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Shape {
+    Empty,
+    Circle(f64),
+    Rectangle(i64, i64),
+    Polygon { corners: u8 },
+}
+
+fn roundtrip<T>(value: T) -> String
+where
+    T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+{
+    let item = to_item(&value).unwrap();
+    let rendered = format!("{}", item);
+    assert_eq!(from_item::<T>(item).unwrap(), value);
+    rendered
+}
+
+#[test]
+fn struct_to_alist() {
+    let server = Server {
+        host: "localhost".into(),
+        port: 80,
+        tags: vec!["a".into(), "b".into()],
+        backup: None,
+    };
+    assert_eq!(
+        roundtrip(server),
+        "((host . \"localhost\") (port . 80) (tags \"a\" \"b\") (backup))"
+    );
+}
+
+#[test]
+fn enum_variants() {
+    assert_eq!(roundtrip(Shape::Empty), "Empty");
+    assert_eq!(roundtrip(Shape::Circle(1.5)), "(Circle . 1.5)");
+    assert_eq!(roundtrip(Shape::Rectangle(2, 3)), "(Rectangle 2 3)");
+    assert_eq!(
+        roundtrip(Shape::Polygon { corners: 5 }),
+        "(Polygon (corners . 5))"
+    );
+}
+
+#[test]
+fn map_keys_are_values() {
+    let mut map = BTreeMap::new();
+    map.insert("one".to_string(), 1);
+    map.insert("two".to_string(), 2);
+    assert_eq!(roundtrip(map), "((\"one\" . 1) (\"two\" . 2))");
+}
+
+#[test]
+fn chars_are_chars() {
+    assert_eq!(roundtrip('λ'), "#\\λ");
+    assert_eq!(roundtrip(vec![' ', '\n']), "(#\\space #\\newline)");
+}
+
+#[test]
+fn char_fields_roundtrip() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Separator {
+        open: char,
+        close: char,
+    }
+    let separator = Separator {
+        open: '(',
+        close: '\t',
+    };
+    assert_eq!(roundtrip(separator), "((open . #\\() (close . #\\tab))");
+    assert_eq!(
+        from_item::<char>(Item::String(String::from("x"))).unwrap(),
+        'x'
+    );
+}
+
+#[test]
+fn large_integers() {
+    assert_eq!(roundtrip(u64::MAX), "18446744073709551615");
+    assert_eq!(roundtrip(i128::MIN), format!("{}", i128::MIN));
+}
+
+#[test]
+fn struct_from_lisp_alist() {
+    let mut machine = crate::vm::Machine::default();
+    let item = crate::run(
+        "(def base (cons (cons 'port 8080) (cons (cons 'host \"example.org\") ())))
+         (cons (cons 'tags ()) base)",
+        &mut machine,
+    )
+    .unwrap();
+    assert_eq!(
+        from_item::<Server>(item).unwrap(),
+        Server {
+            host: "example.org".into(),
+            port: 8080,
+            tags: Vec::new(),
+            backup: None,
+        }
+    );
+}
+
+#[test]
+fn type_mismatch_is_an_error() {
+    let item = Item::Cons(Cons::from(vec![Item::Number(Number::Integer(1))]));
+    assert!(from_item::<String>(item).is_err());
+    assert!(from_item::<u8>(Item::Number(Number::Integer(256))).is_err());
+}
@@ -0,0 +1,47 @@
+use super::*;
+
+fn list(items: Vec<Item>) -> Cons {
+    Cons::from(items)
+}
+
+fn int(i: i64) -> Item {
+    Item::Number(Number::Integer(i))
+}
+
+#[test]
+fn get_returns_elements_within_bounds() {
+    let l = list(vec![int(1), int(2), int(3)]);
+    assert_eq!(l.get(0), Some(&int(1)));
+    assert_eq!(l.try_caddr(), Some(&int(3)));
+    assert_eq!(l.try_cadddr(), None);
+}
+
+#[test]
+fn get_ignores_dotted_tail() {
+    let pair = Cons::new(int(1), int(2));
+    assert_eq!(pair.elements(), &[int(1)]);
+    assert_eq!(pair.try_cadr(), None);
+}
+
+#[test]
+fn nth_cdr_walks_the_list() {
+    let l = list(vec![int(1), int(2), int(3)]);
+    assert_eq!(l.nth_cdr(2), Some(Item::Cons(list(vec![int(3)]))));
+    assert_eq!(l.nth_cdr(3), Some(Item::None));
+    assert_eq!(l.nth_cdr(4), None);
+    assert_eq!(Cons::new(int(1), int(2)).nth_cdr(1), Some(int(2)));
+}
+
+#[test]
+fn string_display_escapes_content() {
+    let string = Item::String(String::from("say \"hi\"\\\n\t\u{1}λ"));
+    assert_eq!(string.to_string(), r#""say \"hi\"\\\n\t\x1;λ""#);
+}
+
+#[test]
+fn name_display_escapes_unreadable_names() {
+    assert_eq!(Item::Name(String::from("tmp")).to_string(), "tmp");
+    assert_eq!(Item::Name(String::from("-")).to_string(), "-");
+    assert_eq!(Item::Name(String::from("tmp,2")).to_string(), "|tmp,2|");
+    assert_eq!(Item::Name(String::from("1st|x")).to_string(), "|1st\\|x|");
+}
@@ -0,0 +1,237 @@
+//! This module renders errors for humans.
+//!
+//! A `Diagnostic` can be generated from every error of the pipeline, and
+//! rendered together with the source code it refers to. Lines and columns
+//! are shown 1-indexed, like in editors:
+//!
+//! ```text
+//! error[E0006]: Wrong amount of arguments. Expexted 1, found 2
+//!  --> example.lisp:2:5
+//!   |
+//! 2 |     (car 1 2))
+//!   |     ^
+//!   = hint: check the number of arguments given to the function
+//! Traceback (most recent call last):
+//!   example.lisp:2:5, in car
+//! ```
+//!
+//! # Example
+//!
+//! ```
+//! use minilisp::diagnostics::Diagnostic;
+//! use minilisp::lexer;
+//!
+//! let source = "(+ 1 \"2)";
+//! let error = lexer::lex(source).unwrap_err();
+//! let rendered = Diagnostic::from(&error).render("<example>", source);
+//! assert!(rendered.contains("<example>:1:"));
+//! ```
+
+use super::lexer::{error::LexerError, Position};
+use super::parser::error::ParserError;
+use super::stdlib::error::EvalErrorCode;
+use super::vm::{TraceFrame, VMError, VMErrorKind};
+use std::fmt::Write;
+
+#[cfg(test)]
+mod tests;
+
+/// An error, along with the information needed for rendering it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    message: String,
+    position: Option<Position>,
+    code: Option<EvalErrorCode>,
+    hint: Option<String>,
+    trace: Vec<TraceFrame>,
+}
+
+impl Diagnostic {
+    pub fn new<T>(message: T) -> Diagnostic
+    where
+        T: Into<String>,
+    {
+        Diagnostic {
+            message: message.into(),
+            position: None,
+            code: None,
+            hint: None,
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn with_position(self, position: Position) -> Diagnostic {
+        Diagnostic {
+            position: Some(position),
+            ..self
+        }
+    }
+
+    pub fn with_code(self, code: EvalErrorCode) -> Diagnostic {
+        Diagnostic {
+            code: Some(code),
+            ..self
+        }
+    }
+
+    pub fn with_hint<T>(self, hint: T) -> Diagnostic
+    where
+        T: Into<String>,
+    {
+        Diagnostic {
+            hint: Some(hint.into()),
+            ..self
+        }
+    }
+
+    pub fn with_trace(self, trace: Vec<TraceFrame>) -> Diagnostic {
+        Diagnostic { trace, ..self }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    pub fn code(&self) -> Option<EvalErrorCode> {
+        self.code
+    }
+
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    /// Renders the diagnostic, where `name` is the name of the source, e.g.
+    /// a file name, and `source` is the source code the error refers to.
+    ///
+    /// Positions on line 0 refer to the end of the source code.
+    pub fn render(&self, name: &str, source: &str) -> String {
+        let mut out = String::new();
+        match self.code {
+            Some(code) => writeln!(out, "error[{}]: {}", code, self.message),
+            None => writeln!(out, "error: {}", self.message),
+        }
+        .unwrap();
+
+        let snippet = self.position.map(|position| locate(source, position));
+        let gutter = match snippet {
+            Some((line, _, _)) => line.to_string().len(),
+            None => 0,
+        };
+        let pad = " ".repeat(gutter);
+        match snippet {
+            Some((line, column, text)) => {
+                // Columns are shown 1-indexed, like in editors
+                writeln!(out, "{}--> {}:{}:{}", pad, name, line, column + 1).unwrap();
+                writeln!(out, "{} |", pad).unwrap();
+                writeln!(out, "{} | {}", line, text).unwrap();
+                writeln!(out, "{} | {}^", pad, indentation(text, column)).unwrap();
+            }
+            None => writeln!(out, "{}--> {}", pad, name).unwrap(),
+        }
+        if let Some(hint) = &self.hint {
+            writeln!(out, "{} = hint: {}", pad, hint).unwrap();
+        }
+        if !self.trace.is_empty() {
+            writeln!(out, "Traceback (most recent call last):").unwrap();
+            for frame in &self.trace {
+                match frame.position() {
+                    Some(position) => {
+                        writeln!(out, "  {}:{}, in {}", name, position, frame.name())
+                    }
+                    None => writeln!(out, "  {}, in {}", name, frame.name()),
+                }
+                .unwrap();
+            }
+        }
+        out
+    }
+}
+
+/// Finds the line and column of the position in the source, along with the
+/// text of the line.
+fn locate(source: &str, position: Position) -> (usize, usize, &str) {
+    let lines: Vec<&str> = source.lines().collect();
+    match lines.get(position.line().wrapping_sub(1)) {
+        Some(text) => (position.line(), position.character(), text),
+        None => {
+            let text = lines.last().copied().unwrap_or("");
+            (lines.len().max(1), text.chars().count(), text)
+        }
+    }
+}
+
+/// Generates the whitespace placing a caret under the given column of the
+/// text, keeping the tabs of the text.
+fn indentation(text: &str, column: usize) -> String {
+    text.chars()
+        .chain(std::iter::repeat(' '))
+        .take(column)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect()
+}
+
+impl From<&LexerError> for Diagnostic {
+    fn from(error: &LexerError) -> Diagnostic {
+        // The message tells what the input ended in the middle of
+        Diagnostic::new(error.message()).with_position(error.position())
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Diagnostic {
+        let diagnostic = Diagnostic::new(error.message()).with_position(error.position());
+        if error.is_eof() {
+            diagnostic.with_hint("a list is missing its closing parenthesis")
+        } else {
+            diagnostic
+        }
+    }
+}
+
+impl From<&VMError> for Diagnostic {
+    fn from(error: &VMError) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(error.message()).with_trace(error.trace().to_vec());
+        if let Some(position) = error.position() {
+            diagnostic = diagnostic.with_position(position);
+        }
+        if let Some(code) = error.code() {
+            diagnostic = diagnostic.with_code(code);
+        }
+        let hint = match error.kind() {
+            VMErrorKind::StackOverflow => {
+                Some("every call, which is not in tail position, uses a stack frame")
+            }
+            VMErrorKind::InstructionLimit => Some("the program might not terminate"),
+            VMErrorKind::AllocationLimit => Some("the program might build an unbounded list"),
+            VMErrorKind::Interrupted => None,
+            VMErrorKind::Runtime => error.code().and_then(code_hint),
+        };
+        match hint {
+            Some(hint) => diagnostic.with_hint(hint),
+            None => diagnostic,
+        }
+    }
+}
+
+fn code_hint(code: EvalErrorCode) -> Option<&'static str> {
+    match code {
+        EvalErrorCode::E0001 | EvalErrorCode::E0004 => {
+            Some("the first element of a list must evaluate to a function")
+        }
+        EvalErrorCode::E0002 => Some("names must be defined with `def` before they are used"),
+        EvalErrorCode::E0005 => Some("`car` and `cdr` require a non-empty list"),
+        EvalErrorCode::E0006 => Some("check the number of arguments given to the function"),
+        EvalErrorCode::E0009 => Some("only names can be bound with `def`"),
+        EvalErrorCode::E0010 | EvalErrorCode::E0012 => {
+            Some("check the types of the arguments given to the function")
+        }
+        EvalErrorCode::E0013 => Some("check that the divisor is not zero"),
+        EvalErrorCode::E0014 => Some("check the shape of the special form"),
+        EvalErrorCode::E0015 => Some("check that the value is within the valid range"),
+        _ => None,
+    }
+}
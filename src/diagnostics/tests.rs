@@ -0,0 +1,112 @@
+use super::super::{convert, lexer, parser, vm};
+use super::*;
+
+fn vm_error(source: &str) -> VMError {
+    let mut symbols = lexer::lex(source).unwrap();
+    let items = convert::convert(parser::parse(&mut symbols).unwrap());
+    let mut machine = vm::Machine::default();
+    for item in items {
+        if let Err(error) = machine.eval(item) {
+            return error;
+        }
+    }
+    panic!("The program did not fail");
+}
+
+#[test]
+fn render_points_at_column() {
+    let source = "(+ 1 2)\n  (car 1 2)";
+    let rendered = Diagnostic::from(&vm_error(source)).render("test.lisp", source);
+    assert_eq!(
+        rendered.lines().take(6).collect::<Vec<_>>(),
+        vec![
+            "error[E0006]: Wrong amount of arguments. Expexted 1, found 2",
+            " --> test.lisp:2:3",
+            "  |",
+            "2 |   (car 1 2)",
+            "  |   ^",
+            "  = hint: check the number of arguments given to the function",
+        ]
+    );
+}
+
+#[test]
+fn unbound_name_has_code() {
+    let diagnostic = Diagnostic::from(&vm_error("(+ 1 x)"));
+    assert_eq!(diagnostic.code(), Some(EvalErrorCode::E0002));
+    assert!(diagnostic.hint().is_some());
+}
+
+#[test]
+fn render_lexer_error() {
+    let source = "(def x\n  \"unterminated)";
+    let error = lexer::lex(source).unwrap_err();
+    assert!(error.is_eof());
+    let rendered = Diagnostic::from(&error).render("<repl>", source);
+    assert!(rendered.starts_with("error: "));
+    assert!(rendered.contains("2 |   \"unterminated)"));
+}
+
+#[test]
+fn parser_eof_points_at_end_of_source() {
+    let source = "(def x\n  (+ 1 2)";
+    let error = parser::parse(&mut lexer::lex(source).unwrap()).unwrap_err();
+    assert!(error.is_eof());
+    let rendered = Diagnostic::from(&error).render("<repl>", source);
+    assert!(rendered.contains("--> <repl>:2:10"));
+    assert!(rendered.contains("  |          ^"));
+}
+
+#[test]
+fn caret_keeps_tabs() {
+    assert_eq!(indentation("\t(car)", 2), "\t ");
+}
+
+#[test]
+fn render_matches_module_docs() {
+    let source = "(begin\n    (car 1 2))";
+    let rendered = Diagnostic::from(&vm_error(source)).render("example.lisp", source);
+    assert_eq!(
+        rendered,
+        "error[E0006]: Wrong amount of arguments. Expexted 1, found 2
+ --> example.lisp:2:5
+  |
+2 |     (car 1 2))
+  |     ^
+  = hint: check the number of arguments given to the function
+Traceback (most recent call last):
+  example.lisp:2:5, in car
+"
+    );
+}
+
+#[test]
+fn unparseable_arguments_hint_at_types() {
+    let diagnostic = Diagnostic::from(&vm_error("(+ 1 \"a\")"));
+    assert_eq!(diagnostic.code(), Some(EvalErrorCode::E0010));
+    assert!(diagnostic
+        .render("test.lisp", "(+ 1 \"a\")")
+        .contains("= hint: check the types of the arguments given to the function"));
+}
+
+#[test]
+fn def_of_non_name_hints_at_names() {
+    let diagnostic = Diagnostic::from(&vm_error("(def 1 2)"));
+    assert_eq!(
+        diagnostic.hint(),
+        Some("only names can be bound with `def`")
+    );
+}
+
+#[test]
+fn lexer_eof_has_no_string_hint() {
+    let source = "#| never closed";
+    let error = lexer::lex(source).unwrap_err();
+    assert!(error.is_eof());
+    let diagnostic = Diagnostic::from(&error);
+    assert_eq!(
+        diagnostic.message(),
+        "Expected |# to end the block comment, found EOF"
+    );
+    assert_eq!(diagnostic.hint(), None);
+}
@@ -0,0 +1,132 @@
+use super::diagnostics::Diagnostic;
+use super::lexer::{error::LexerError, Position};
+use super::parser::error::ParserError;
+use super::stdlib::error::{EvalError, EvalErrorCode};
+use super::vm::VMError;
+use std::{fmt, io};
+
+/// An error from any stage of evaluating source code.
+#[derive(Debug)]
+pub enum Error {
+    Lexer(LexerError),
+    Parser(ParserError),
+    VM(VMError),
+    Eval(EvalError),
+    /// The source code could not be read.
+    Io(io::Error),
+}
+
+impl Error {
+    /// Returns the position in the source code, where the error occurred,
+    /// if it is known.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Error::Lexer(e) => Some(e.position()),
+            Error::Parser(e) => Some(e.position()),
+            Error::VM(e) => e.position(),
+            Error::Eval(e) => e.position,
+            Error::Io(_) => None,
+        }
+    }
+
+    /// Returns the code of the error, if it has one.
+    pub fn code(&self) -> Option<EvalErrorCode> {
+        match self {
+            Error::Lexer(_) | Error::Parser(_) | Error::Io(_) => None,
+            Error::VM(e) => e.code(),
+            Error::Eval(e) => Some(e.code),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Error::Lexer(e) => e.message().into(),
+            Error::Parser(e) => e.message().into(),
+            Error::VM(e) => e.message().into(),
+            Error::Eval(e) => e.message.clone(),
+            Error::Io(e) => e.to_string(),
+        }
+    }
+
+    /// Returns true if the error was caused by the source code ending in the
+    /// middle of an expression, meaning that more input could make it valid.
+    pub fn is_eof(&self) -> bool {
+        match self {
+            Error::Lexer(e) => e.is_eof(),
+            Error::Parser(e) => e.is_eof(),
+            Error::VM(_) | Error::Eval(_) | Error::Io(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Lexer(e) => write!(f, "{}", e),
+            Error::Parser(e) => write!(f, "{}", e),
+            Error::VM(e) => write!(f, "{}", e),
+            Error::Eval(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Lexer(e) => Some(e),
+            Error::Parser(e) => Some(e),
+            Error::VM(e) => Some(e),
+            Error::Eval(e) => Some(e),
+            Error::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<LexerError> for Error {
+    fn from(error: LexerError) -> Error {
+        Error::Lexer(error)
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(error: ParserError) -> Error {
+        Error::Parser(error)
+    }
+}
+
+impl From<VMError> for Error {
+    fn from(error: VMError) -> Error {
+        Error::VM(error)
+    }
+}
+
+impl From<EvalError> for Error {
+    fn from(error: EvalError) -> Error {
+        Error::Eval(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Diagnostic {
+        match error {
+            Error::Lexer(e) => Diagnostic::from(e),
+            Error::Parser(e) => Diagnostic::from(e),
+            Error::VM(e) => Diagnostic::from(e),
+            Error::Eval(e) => {
+                let diagnostic = Diagnostic::new(e.message.as_str()).with_code(e.code);
+                match e.position {
+                    Some(position) => diagnostic.with_position(position),
+                    None => diagnostic,
+                }
+            }
+            Error::Io(e) => Diagnostic::new(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use super::datastructure::{Cons, EnvItem, Item};
+use super::vm::{Machine, MachineOptions, NativeFunction};
+use super::{run, Error};
+use std::fs;
+use std::path::Path;
+
+#[cfg(test)]
+mod tests;
+
+/// An interpreter for embedding minilisp in a host application.
+///
+/// The interpreter keeps its global bindings between calls, so a script can
+/// be loaded once and its functions called from the host afterwards.
+///
+/// # Example
+///
+/// ```
+/// use minilisp::datastructure::{Item, Number};
+/// use minilisp::Interpreter;
+///
+/// let mut interpreter = Interpreter::new();
+/// interpreter.set_global("base", Item::Number(Number::Integer(40)));
+/// interpreter
+///     .eval_str("(def add-base (lambda (x) (+ base x)))")
+///     .unwrap();
+/// let result = interpreter
+///     .call("add-base", vec![Item::Number(Number::Integer(2))])
+///     .unwrap();
+/// assert_eq!(result, Item::Number(Number::Integer(42)));
+/// ```
+pub struct Interpreter {
+    machine: Machine,
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter {
+            machine: Machine::default(),
+        }
+    }
+
+    /// Generates a new interpreter, which is limited by the given options.
+    pub fn with_options(options: MachineOptions) -> Interpreter {
+        Interpreter {
+            machine: Machine::with_options(options),
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        &mut self.machine
+    }
+
+    /// Evaluates every expression of the source code, and returns the value
+    /// of the last one.
+    pub fn eval_str(&mut self, source: &str) -> Result<Item, Error> {
+        run(source, &mut self.machine)
+    }
+
+    /// Evaluates every expression of the file, and returns the value of the
+    /// last one.
+    pub fn eval_file<P>(&mut self, path: P) -> Result<Item, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let source = fs::read_to_string(path)?;
+        self.eval_str(&source)
+    }
+
+    /// Calls the function bound to the name with the given arguments.
+    /// The arguments are passed as data, and are not evaluated.
+    pub fn call<T>(&mut self, name: T, args: Vec<Item>) -> Result<Item, Error>
+    where
+        T: Into<String>,
+    {
+        let mut list = vec![Item::Name(name.into())];
+        list.extend(args.into_iter().map(quote));
+        Ok(self.machine.eval(Item::Cons(Cons::from(list)))?.into())
+    }
+
+    /// Defines a global function, which calls the Rust function. See the
+    /// `vm::native` module for the supported signatures.
+    pub fn register_fn<T, Args, F>(&mut self, name: T, function: F)
+    where
+        T: Into<String>,
+        F: NativeFunction<Args> + 'static,
+    {
+        // Defining a global value cannot fail
+        let _ = self.machine.register_fn(name, function);
+    }
+
+    /// Returns the value bound to the global name, if any. Functions are
+    /// returned as their name.
+    pub fn get_global(&self, name: &str) -> Option<Item> {
+        self.machine.lookup_global(name).map(Item::from)
+    }
+
+    /// Binds the value to the global name, replacing any previous binding.
+    pub fn set_global<T>(&mut self, name: T, value: Item)
+    where
+        T: Into<String>,
+    {
+        // Defining a global value cannot fail
+        let _ = self.machine.define(name, EnvItem::Data(value));
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+fn quote(item: Item) -> Item {
+    Item::Cons(Cons::from(vec![Item::Name(String::from("quote")), item]))
+}
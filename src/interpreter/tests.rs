@@ -0,0 +1,59 @@
+use super::super::datastructure::Number;
+use super::*;
+
+#[test]
+fn globals_persist_between_evals() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval_str("(def x 5)").unwrap();
+    assert_eq!(
+        interpreter.eval_str("(* x 2)").unwrap(),
+        Item::Number(Number::Integer(10))
+    );
+}
+
+#[test]
+fn get_and_set_global() {
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.get_global("answer"), None);
+    interpreter.set_global("answer", Item::Number(Number::Integer(42)));
+    assert_eq!(
+        interpreter.get_global("answer"),
+        Some(Item::Number(Number::Integer(42)))
+    );
+    assert_eq!(
+        interpreter.eval_str("answer").unwrap(),
+        Item::Number(Number::Integer(42))
+    );
+}
+
+#[test]
+fn call_does_not_evaluate_arguments() {
+    let mut interpreter = Interpreter::new();
+    interpreter
+        .eval_str("(def first (lambda (l) (car l)))")
+        .unwrap();
+    let list = Item::Cons(Cons::from(vec![
+        Item::Name("not-bound".into()),
+        Item::Number(Number::Integer(2)),
+    ]));
+    assert_eq!(
+        interpreter.call("first", vec![list]).unwrap(),
+        Item::Name("not-bound".into())
+    );
+}
+
+#[test]
+fn call_unbound_function() {
+    assert_matches!(
+        Interpreter::new().call("missing", Vec::new()),
+        Err(Error::VM(_))
+    );
+}
+
+#[test]
+fn eval_missing_file() {
+    assert_matches!(
+        Interpreter::new().eval_file("/this/file/does/not/exist.lisp"),
+        Err(Error::Io(_))
+    );
+}
@@ -1,9 +1,11 @@
 use super::tracking::Position;
+use std::fmt;
 
 #[derive(Debug, PartialEq)]
 pub struct LexerError {
     position: Position,
     message: String,
+    eof: bool,
 }
 
 impl LexerError {
@@ -11,6 +13,37 @@ impl LexerError {
         LexerError {
             position: pos,
             message: String::from(msg),
+            eof: false,
         }
     }
+
+    /// Generates an error for input, which ended in the middle of a symbol.
+    pub fn unexpected_eof(pos: Position, msg: &str) -> LexerError {
+        LexerError {
+            eof: true,
+            ..LexerError::new(pos, msg)
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns true if the error was caused by the input ending too early,
+    /// meaning that more input could make it valid.
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
 }
+
+impl fmt::Display for LexerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for LexerError {}
@@ -7,6 +7,8 @@ mod tests;
 pub mod error;
 mod tracking;
 
+use num_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
 pub use tracking::Position;
 
 /// Enum over the different symbols we can lex.
@@ -17,13 +19,21 @@ pub use tracking::Position;
 pub enum Symbol {
     /// Represents the quote `'`
     Quote(Position),
+    /// Represents the backquote `` ` ``
+    Quasiquote(Position),
+    /// Represents the comma `,`
+    Unquote(Position),
+    /// Represents the comma followed by an at sign `,@`
+    UnquoteSplicing(Position),
     /// Represents the left parenthesis `(`
     LParen(Position),
     /// Represents the right parenthesis `)`
     RParen(Position),
     /// Represents bound names
     /// ### Syntax
-    /// Matched by the following regex: `[^"#0-9\s][^"\s]*`
+    /// Matched by the following regex: `[^"#;|0-9\s][^";\s]*`, or written
+    /// between bars like `|a name|`, where `\|` and `\\` are escapes. Any
+    /// name can be written between bars.
     Name(Position, String),
     /// Represents any of the literals defined in `enum Literal`
     Primitive(Position, Literal),
@@ -34,6 +44,9 @@ impl Symbol {
     pub fn position(&self) -> Position {
         *match self {
             Symbol::Quote(p) => p,
+            Symbol::Quasiquote(p) => p,
+            Symbol::Unquote(p) => p,
+            Symbol::UnquoteSplicing(p) => p,
             Symbol::LParen(p) => p,
             Symbol::RParen(p) => p,
             Symbol::Name(p, _) => p,
@@ -45,18 +58,42 @@ impl Symbol {
 /// Enum over the literal types that we can lex.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
-    /// This literal encodes numbers. Currently it only supports integers.
+    /// This literal encodes integers.
     /// ### Syntax
     /// The symbol is matched by the following regex: `-?[0-9]+`
     Number(i64),
+    /// This literal encodes the integers that are too large for `Number`.
+    /// ### Syntax
+    /// The same as `Number`.
+    BigInteger(BigInt),
+    /// This literal encodes exact fractions, as a numerator and a denominator.
+    /// ### Syntax
+    /// The symbol is matched by the following regex: `-?[0-9]+/[0-9]+`
+    Rational(BigInt, BigInt),
+    /// This literal encodes floating point numbers.
+    /// ### Syntax
+    /// The symbol is matched by the following regex:
+    /// `-?[0-9]+(\.[0-9]*)?([eE][+-]?[0-9]+)?`
+    Real(f64),
     /// This literal encodes boolean values.
     /// ### Syntax
     /// The symbol is matched by the following regex: `#[tf]`
     Boolean(bool),
     /// This literal encodes string values.
     /// ### Syntax
-    /// The symbol is matched by the following regex: `"(.*(\\")?)*"`
+    /// The symbol is matched by the following regex: `"([^"\\]|\\.)*"`,
+    /// where `\n`, `\t`, `\r`, `\\`, `\"` and `\0` are escape sequences, as
+    /// well as `\xNN;` and `\u{NNNN}` for the character of the hexadecimal
+    /// code point. A raw string is written as `#r"..."`, and contains no
+    /// escape sequences. It can contain `"` when delimited by hashes, like
+    /// `#r#"..."#`.
     String(String),
+    /// This literal encodes characters.
+    /// ### Syntax
+    /// The symbol is matched by the following regex: `#\\(.|[a-z]+|x[0-9a-fA-F]+)`,
+    /// where a name stands for a character like `#\space` or `#\newline`,
+    /// and `#\xNN` for the character of the hexadecimal code point.
+    Char(char),
     /// This literal is currently not supported yet.
     None,
 }
@@ -68,6 +105,10 @@ struct Buffers {
 
 /// Turns a string into a vector of symbols.
 ///
+/// Comments are skipped: `;` comments out the rest of the line, `#| ... |#`
+/// comments out a block, which can contain other block comments, and `#;`
+/// comments out the datum following it.
+///
 /// # Example
 ///
 /// ```
@@ -95,6 +136,8 @@ pub fn lex(input: &str) -> Result<Vec<Symbol>, error::LexerError> {
         symbols: Vec::new(),
         buffer: String::new(),
     };
+    // The index of the first symbol of the datum following each `#;`
+    let mut datum_comments = Vec::new();
 
     let seq = input.chars().peekable();
     let mut cursor = tracking::Cursor::new(seq);
@@ -102,13 +145,7 @@ pub fn lex(input: &str) -> Result<Vec<Symbol>, error::LexerError> {
         let c = *x;
 
         if c.is_whitespace() {
-            if !buffers.buffer.is_empty() {
-                buffers.symbols.push(Symbol::Name(
-                    cursor.pos().start_of(buffers.buffer.as_str()),
-                    buffers.buffer.clone(),
-                ));
-                buffers.buffer.clear();
-            }
+            push_name(&mut buffers, &cursor);
             cursor.next();
             continue;
         }
@@ -116,11 +153,41 @@ pub fn lex(input: &str) -> Result<Vec<Symbol>, error::LexerError> {
         if match c {
             // Matching literals
             '"' => push_symbol(&mut buffers, collect_string(&mut cursor)?, &cursor),
+            ',' => push_symbol(&mut buffers, collect_unquote(&mut cursor), &cursor),
+            '|' if buffers.buffer.is_empty() => {
+                push_symbol(&mut buffers, collect_bar_name(&mut cursor)?, &cursor)
+            }
+            ';' => {
+                push_name(&mut buffers, &cursor);
+                skip_line_comment(&mut cursor);
+                false
+            }
             '#' if buffers.buffer.is_empty() => {
-                push_symbol(&mut buffers, collect_bool(&mut cursor)?, &cursor)
+                let startpos = cursor.pos();
+                cursor.next();
+                match cursor.peek() {
+                    Some('|') => {
+                        skip_block_comment(&mut cursor, startpos)?;
+                        false
+                    }
+                    Some(';') => {
+                        cursor.next();
+                        datum_comments.push((buffers.symbols.len(), startpos));
+                        false
+                    }
+                    Some('\\') => {
+                        let character = collect_char(&mut cursor, startpos)?;
+                        push_symbol(&mut buffers, character, &cursor)
+                    }
+                    Some('r') => {
+                        let string = collect_raw_string(&mut cursor, startpos)?;
+                        push_symbol(&mut buffers, string, &cursor)
+                    }
+                    _ => push_symbol(&mut buffers, collect_bool(&mut cursor, startpos)?, &cursor),
+                }
             }
             n if n.is_ascii_digit() && (buffers.buffer.is_empty() || buffers.buffer == "-") => {
-                let number = collect_number(&mut cursor, &mut buffers.buffer);
+                let number = collect_number(&mut cursor, &mut buffers.buffer)?;
                 push_symbol(&mut buffers, number, &cursor)
             }
             _ => true,
@@ -128,6 +195,7 @@ pub fn lex(input: &str) -> Result<Vec<Symbol>, error::LexerError> {
             match c {
                 // Matching Symbols
                 '\'' => push_symbol(&mut buffers, Symbol::Quote(cursor.pos()), &cursor),
+                '`' => push_symbol(&mut buffers, Symbol::Quasiquote(cursor.pos()), &cursor),
                 '(' => push_symbol(&mut buffers, Symbol::LParen(cursor.pos()), &cursor),
                 ')' => push_symbol(&mut buffers, Symbol::RParen(cursor.pos()), &cursor),
                 _ => {
@@ -138,16 +206,24 @@ pub fn lex(input: &str) -> Result<Vec<Symbol>, error::LexerError> {
             cursor.next();
         }
     }
-    if !buffers.buffer.is_empty() {
-        buffers.symbols.push(Symbol::Name(
-            cursor.pos().start_of(buffers.buffer.as_str()),
-            buffers.buffer.clone(),
-        ));
+    push_name(&mut buffers, &cursor);
+    // The innermost datum comments are removed first, so `#; #; a b`
+    // comments out both `a` and `b`
+    for (start, pos) in datum_comments.into_iter().rev() {
+        let end = datum_end(&buffers.symbols[start..], pos)?;
+        buffers.symbols.drain(start..start + end);
     }
     Ok(buffers.symbols)
 }
 
 fn push_symbol(buffers: &mut Buffers, symbol: Symbol, seq: &tracking::Cursor) -> bool {
+    push_name(buffers, seq);
+    buffers.symbols.push(symbol);
+    false
+}
+
+/// Pushes the name in the buffer, if any, as a symbol.
+fn push_name(buffers: &mut Buffers, seq: &tracking::Cursor) {
     if !buffers.buffer.is_empty() {
         buffers.symbols.push(Symbol::Name(
             seq.pos().start_of(buffers.buffer.as_str()),
@@ -155,24 +231,174 @@ fn push_symbol(buffers: &mut Buffers, symbol: Symbol, seq: &tracking::Cursor) ->
         ));
         buffers.buffer.clear();
     }
-    buffers.symbols.push(symbol);
-    false
 }
 
-fn collect_number(seq: &mut tracking::Cursor, prev: &mut String) -> Symbol {
+/// Skips a line comment, up to the end of the line.
+fn skip_line_comment(seq: &mut tracking::Cursor) {
+    while let Some(c) = seq.peek() {
+        if *c == '\n' {
+            break;
+        }
+        seq.next();
+    }
+}
+
+/// Skips a block comment, where `seq` is at the `|` following the `#`.
+/// Block comments can be nested, so every `#|` needs its own `|#`.
+fn skip_block_comment(
+    seq: &mut tracking::Cursor,
+    startpos: Position,
+) -> Result<(), error::LexerError> {
+    seq.next();
+    let mut depth = 1;
+    while depth > 0 {
+        match seq.next() {
+            Some('|') if seq.peek() == Some(&'#') => {
+                seq.next();
+                depth -= 1;
+            }
+            Some('#') if seq.peek() == Some(&'|') => {
+                seq.next();
+                depth += 1;
+            }
+            Some(_) => {}
+            None => {
+                return Err(error::LexerError::unexpected_eof(
+                    startpos,
+                    "Expected |# to end the block comment, found EOF",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the number of symbols of the first datum of the symbols, which
+/// is commented out by the `#;` at the position.
+fn datum_end(symbols: &[Symbol], pos: Position) -> Result<usize, error::LexerError> {
+    let mut depth = 0;
+    for (i, symbol) in symbols.iter().enumerate() {
+        match symbol {
+            Symbol::Quote(_)
+            | Symbol::Quasiquote(_)
+            | Symbol::Unquote(_)
+            | Symbol::UnquoteSplicing(_) => continue,
+            Symbol::LParen(_) => depth += 1,
+            Symbol::RParen(_) if depth == 0 => {
+                return Err(error::LexerError::new(
+                    pos,
+                    "Expected a datum after #;, found )",
+                ))
+            }
+            Symbol::RParen(_) => depth -= 1,
+            Symbol::Name(_, _) | Symbol::Primitive(_, _) => {}
+        }
+        if depth == 0 {
+            return Ok(i + 1);
+        }
+    }
+    Err(error::LexerError::unexpected_eof(
+        pos,
+        "Expected a datum after #;, found EOF",
+    ))
+}
+
+/// Collects a number literal, which is either an integer, a rational or a real.
+fn collect_number(
+    seq: &mut tracking::Cursor,
+    prev: &mut String,
+) -> Result<Symbol, error::LexerError> {
     let startpos = seq.pos().start_of(prev);
     let mut buffer = prev.clone();
     prev.clear();
+    collect_digits(seq, &mut buffer);
+
+    let literal = match seq.peek() {
+        Some('/') => {
+            seq.next();
+            let mut denominator = String::new();
+            if collect_digits(seq, &mut denominator) == 0 {
+                return Err(error::LexerError::new(
+                    seq.pos(),
+                    "Expected the denominator of the rational",
+                ));
+            }
+            let denominator = parse_number(&denominator, startpos)?;
+            if denominator.is_zero() {
+                return Err(error::LexerError::new(
+                    startpos,
+                    "The denominator of a rational cannot be zero",
+                ));
+            }
+            Literal::Rational(parse_number(&buffer, startpos)?, denominator)
+        }
+        Some('.') | Some('e') | Some('E') => {
+            if let Some('.') = seq.peek() {
+                buffer.push('.');
+                seq.next();
+                collect_digits(seq, &mut buffer);
+            }
+            if let Some('e') | Some('E') = seq.peek() {
+                buffer.push('e');
+                seq.next();
+                if let Some('-') | Some('+') = seq.peek() {
+                    buffer.push(seq.next().unwrap());
+                }
+                if collect_digits(seq, &mut buffer) == 0 {
+                    return Err(error::LexerError::new(
+                        seq.pos(),
+                        "Expected the digits of the exponent",
+                    ));
+                }
+            }
+            match buffer.parse() {
+                Ok(real) => Literal::Real(real),
+                Err(_) => {
+                    return Err(error::LexerError::new(
+                        startpos,
+                        format!("'{}' is not a valid number", buffer).as_str(),
+                    ))
+                }
+            }
+        }
+        _ => {
+            let number = parse_number(&buffer, startpos)?;
+            match number.to_i64() {
+                Some(number) => Literal::Number(number),
+                None => Literal::BigInteger(number),
+            }
+        }
+    };
+    Ok(Symbol::Primitive(startpos, literal))
+}
+
+/// Pushes digits onto the buffer until a non digit is found.
+/// Returns the number of digits collected.
+fn collect_digits(seq: &mut tracking::Cursor, buffer: &mut String) -> usize {
+    let mut count = 0;
     while seq.peek().unwrap_or(&'a').is_ascii_digit() {
         // Using 'a' as a random non digit character
         buffer.push(seq.next().unwrap());
+        count += 1;
     }
-    Symbol::Primitive(startpos, Literal::Number(buffer.parse().unwrap()))
+    count
 }
 
-fn collect_bool(seq: &mut tracking::Cursor) -> Result<Symbol, error::LexerError> {
-    let startpos = seq.pos();
-    seq.next();
+fn parse_number(buffer: &str, pos: Position) -> Result<BigInt, error::LexerError> {
+    match buffer.parse() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(error::LexerError::new(
+            pos,
+            format!("'{}' is not a valid number", buffer).as_str(),
+        )),
+    }
+}
+
+/// Collects a boolean, where `seq` is after the `#` at `startpos`.
+fn collect_bool(
+    seq: &mut tracking::Cursor,
+    startpos: Position,
+) -> Result<Symbol, error::LexerError> {
     match seq.next() {
         Some('t') => Ok(Symbol::Primitive(startpos, Literal::Boolean(true))),
         Some('f') => Ok(Symbol::Primitive(startpos, Literal::Boolean(false))),
@@ -187,35 +413,271 @@ fn collect_bool(seq: &mut tracking::Cursor) -> Result<Symbol, error::LexerError>
     }
 }
 
-/// Collects a string litteral based on the following syntax: "(.*(\\")?)*"
-fn collect_string(seq: &mut tracking::Cursor) -> Result<Symbol, error::LexerError> {
+/// The names of the characters, which can be written like `#\space`.
+const CHAR_NAMES: [(&str, char); 9] = [
+    ("alarm", '\u{7}'),
+    ("backspace", '\u{8}'),
+    ("delete", '\u{7f}'),
+    ("escape", '\u{1b}'),
+    ("newline", '\n'),
+    ("null", '\0'),
+    ("return", '\r'),
+    ("space", ' '),
+    ("tab", '\t'),
+];
+
+/// Returns the name of the character, if it has one.
+pub fn char_name(c: char) -> Option<&'static str> {
+    CHAR_NAMES
+        .iter()
+        .find(|(_, named)| *named == c)
+        .map(|(name, _)| *name)
+}
+
+/// Collects a character literal, where `seq` is at the `\` following the
+/// `#` at `startpos`. The first character is always part of the literal, so
+/// `#\(` is the character `(`, and any following characters, up to a
+/// delimiter, make up the name of the character.
+fn collect_char(
+    seq: &mut tracking::Cursor,
+    startpos: Position,
+) -> Result<Symbol, error::LexerError> {
+    seq.next();
+    let mut buffer = match seq.next() {
+        Some(c) => c.to_string(),
+        None => {
+            return Err(error::LexerError::unexpected_eof(
+                seq.pos(),
+                "Expected a character, found EOF",
+            ))
+        }
+    };
+    while let Some(c) = seq.peek() {
+        if c.is_whitespace() || "()\";'`,".contains(*c) {
+            break;
+        }
+        buffer.push(*c);
+        seq.next();
+    }
+
+    let mut chars = buffer.chars();
+    let literal = match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => match CHAR_NAMES.iter().find(|(name, _)| *name == buffer) {
+            Some((_, c)) => Some(*c),
+            None if buffer.starts_with('x') => u32::from_str_radix(&buffer[1..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            None => None,
+        },
+    };
+    match literal {
+        Some(c) => Ok(Symbol::Primitive(startpos, Literal::Char(c))),
+        None => Err(error::LexerError::new(
+            startpos,
+            format!("'#\\{}' is not a valid character", buffer).as_str(),
+        )),
+    }
+}
+
+/// Collects a name written between bars, like `|a name|`.
+fn collect_bar_name(seq: &mut tracking::Cursor) -> Result<Symbol, error::LexerError> {
     let startpos = seq.pos();
     let mut buffer = String::new();
     seq.next();
     loop {
-        if match seq.peek() {
-            Some(x) => *x,
-            None => return Err(error::LexerError::new(seq.pos(), "Expected \", found EOF")),
-        } == '\\'
-        {
-            seq.next();
-            buffer.push(match seq.next() {
-                Some(x) => x,
-                None => {
+        match seq.next() {
+            Some('|') => break,
+            Some('\\') => match seq.next() {
+                Some(c @ '|') | Some(c @ '\\') => buffer.push(c),
+                Some(c) => {
                     return Err(error::LexerError::new(
+                        startpos,
+                        format!("Unknown escape sequence \\{} in a name", c).as_str(),
+                    ))
+                }
+                None => {
+                    return Err(error::LexerError::unexpected_eof(
                         seq.pos(),
                         "Expected character, found EOF",
                     ))
                 }
-            });
+            },
+            Some(c) => buffer.push(c),
+            None => {
+                return Err(error::LexerError::unexpected_eof(
+                    seq.pos(),
+                    "Expected | to end the name, found EOF",
+                ))
+            }
         }
+    }
+    Ok(Symbol::Name(startpos, buffer))
+}
+
+/// Returns true if the name reads back as the same name when written as it
+/// is, rather than between bars.
+pub fn is_plain_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_plain = match (chars.next(), chars.next()) {
+        (None, _) => false,
+        (Some('#'), _) | (Some('|'), _) => false,
+        (Some(c), _) if c.is_ascii_digit() => false,
+        (Some('-'), Some(c)) if c.is_ascii_digit() => false,
+        _ => true,
+    };
+    starts_plain
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "()\"';`,".contains(c))
+}
+
+/// Collects either an unquote `,` or an unquote-splicing `,@`.
+fn collect_unquote(seq: &mut tracking::Cursor) -> Symbol {
+    let startpos = seq.pos();
+    seq.next();
+    if let Some('@') = seq.peek() {
+        seq.next();
+        Symbol::UnquoteSplicing(startpos)
+    } else {
+        Symbol::Unquote(startpos)
+    }
+}
+
+/// Collects a string literal, and replaces its escape sequences.
+fn collect_string(seq: &mut tracking::Cursor) -> Result<Symbol, error::LexerError> {
+    let startpos = seq.pos();
+    let mut buffer = String::new();
+    seq.next();
+    loop {
         match seq.next() {
             Some('"') => break,
+            Some('\\') => buffer.push(collect_escape(seq)?),
             Some(c) => buffer.push(c),
             None => {
+                return Err(error::LexerError::unexpected_eof(
+                    seq.pos(),
+                    "Expected \", found EOF",
+                ))
+            }
+        }
+    }
+    Ok(Symbol::Primitive(startpos, Literal::String(buffer)))
+}
+
+/// Collects the escape sequence following a `\`, and returns the character
+/// it stands for.
+fn collect_escape(seq: &mut tracking::Cursor) -> Result<char, error::LexerError> {
+    let startpos = seq.pos();
+    match seq.next() {
+        Some('n') => Ok('\n'),
+        Some('t') => Ok('\t'),
+        Some('r') => Ok('\r'),
+        Some('0') => Ok('\0'),
+        Some('\\') => Ok('\\'),
+        Some('"') => Ok('"'),
+        Some('x') => collect_code_point(seq, startpos, ';'),
+        Some('u') => match seq.next() {
+            Some('{') => collect_code_point(seq, startpos, '}'),
+            _ => Err(error::LexerError::new(
+                startpos,
+                "Expected \\u{...} for a Unicode escape",
+            )),
+        },
+        Some(c) => Err(error::LexerError::new(
+            startpos,
+            format!("Unknown escape sequence \\{}", c).as_str(),
+        )),
+        None => Err(error::LexerError::unexpected_eof(
+            seq.pos(),
+            "Expected character, found EOF",
+        )),
+    }
+}
+
+/// Collects the hexadecimal digits of a code point up to the terminator,
+/// and returns its character.
+fn collect_code_point(
+    seq: &mut tracking::Cursor,
+    startpos: Position,
+    terminator: char,
+) -> Result<char, error::LexerError> {
+    let mut digits = String::new();
+    loop {
+        match seq.next() {
+            Some(c) if c == terminator => break,
+            Some(c) if c.is_ascii_hexdigit() => digits.push(c),
+            Some(c) => {
                 return Err(error::LexerError::new(
+                    startpos,
+                    format!(
+                        "Expected a hexadecimal digit or {}, found {}",
+                        terminator, c
+                    )
+                    .as_str(),
+                ))
+            }
+            None => {
+                return Err(error::LexerError::unexpected_eof(
+                    seq.pos(),
+                    format!("Expected {}, found EOF", terminator).as_str(),
+                ))
+            }
+        }
+    }
+    match u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(char::from_u32)
+    {
+        Some(c) => Ok(c),
+        None => Err(error::LexerError::new(
+            startpos,
+            format!("'{}' is not a valid code point", digits).as_str(),
+        )),
+    }
+}
+
+/// Collects a raw string like `#r"..."` or `#r#"..."#`, where `seq` is at
+/// the `r` following the `#` at `startpos`. The string ends at the first `"`
+/// followed by as many hashes as it started with.
+fn collect_raw_string(
+    seq: &mut tracking::Cursor,
+    startpos: Position,
+) -> Result<Symbol, error::LexerError> {
+    seq.next();
+    let mut hashes = 0;
+    while let Some('#') = seq.peek() {
+        seq.next();
+        hashes += 1;
+    }
+    if seq.next() != Some('"') {
+        return Err(error::LexerError::new(
+            startpos,
+            "Expected \" to start the raw string",
+        ));
+    }
+    let mut buffer = String::new();
+    loop {
+        match seq.next() {
+            Some('"') => {
+                let mut closing = 0;
+                while closing < hashes && seq.peek() == Some(&'#') {
+                    seq.next();
+                    closing += 1;
+                }
+                if closing == hashes {
+                    break;
+                }
+                buffer.push('"');
+                for _ in 0..closing {
+                    buffer.push('#');
+                }
+            }
+            Some(c) => buffer.push(c),
+            None => {
+                return Err(error::LexerError::unexpected_eof(
                     seq.pos(),
-                    "No ending double quote for string!",
+                    "Expected the end of the raw string, found EOF",
                 ))
             }
         }
@@ -73,6 +73,51 @@ fn negative_number() {
     );
 }
 
+#[test]
+fn real_number() {
+    assert_eq!(
+        vec![
+            Symbol::Primitive(Position::at(1, 0), Literal::Real(1.5)),
+            Symbol::Primitive(Position::at(1, 4), Literal::Real(-2.0)),
+            Symbol::Primitive(Position::at(1, 8), Literal::Real(1e10)),
+            Symbol::Primitive(Position::at(1, 13), Literal::Real(2.5e-3)),
+        ],
+        lex("1.5 -2. 1e10 2.5E-3").unwrap()
+    );
+}
+
+#[test]
+fn rational_number() {
+    assert_eq!(
+        vec![
+            Symbol::Primitive(Position::at(1, 0), Literal::Rational(3.into(), 4.into())),
+            Symbol::Primitive(Position::at(1, 4), Literal::Rational((-1).into(), 2.into())),
+        ],
+        lex("3/4 -1/2").unwrap()
+    );
+}
+
+#[test]
+fn big_integer() {
+    assert_eq!(
+        vec![Symbol::Primitive(
+            Position::at(1, 0),
+            Literal::BigInteger("-1234567890123456789012345".parse().unwrap())
+        )],
+        lex("-1234567890123456789012345").unwrap()
+    );
+}
+
+#[test]
+fn rational_zero_denominator() {
+    assert_matches!(lex("1/0"), Err(_));
+}
+
+#[test]
+fn missing_exponent() {
+    assert_matches!(lex("1e"), Err(_));
+}
+
 #[test]
 fn boolean_only_true() {
     assert_eq!(
@@ -99,3 +144,123 @@ fn boolean_only_false() {
 fn boolean_fail() {
     assert_matches!(lex("#j"), Err(_));
 }
+
+#[test]
+fn lex_quasiquote() {
+    assert_eq!(
+        vec![
+            Symbol::Quasiquote(Position::at(1, 0)),
+            Symbol::LParen(Position::at(1, 1)),
+            Symbol::Name(Position::at(1, 2), String::from("a")),
+            Symbol::Unquote(Position::at(1, 4)),
+            Symbol::Name(Position::at(1, 5), String::from("b")),
+            Symbol::UnquoteSplicing(Position::at(1, 7)),
+            Symbol::Name(Position::at(1, 9), String::from("c")),
+            Symbol::RParen(Position::at(1, 10)),
+        ],
+        lex("`(a ,b ,@c)").unwrap()
+    );
+}
+
+#[test]
+fn line_comment() {
+    assert_eq!(
+        vec![
+            Symbol::Name(Position::at(1, 0), String::from("a")),
+            Symbol::Name(Position::at(2, 1), String::from("b")),
+        ],
+        lex("a; comment \"not a string\n b").unwrap()
+    );
+}
+
+#[test]
+fn nested_block_comment() {
+    assert_eq!(
+        vec![
+            Symbol::Name(Position::at(1, 0), String::from("a")),
+            Symbol::Name(Position::at(3, 4), String::from("b")),
+        ],
+        lex("a #| outer\n #| inner |# ;\n |# b").unwrap()
+    );
+}
+
+#[test]
+fn unterminated_block_comment() {
+    let error = lex("a\n #| #| |#").unwrap_err();
+    assert_eq!(error.position(), Position::at(2, 1));
+    assert!(error.is_eof());
+}
+
+#[test]
+fn datum_comment() {
+    assert_eq!(
+        vec![
+            Symbol::LParen(Position::at(1, 0)),
+            Symbol::Name(Position::at(1, 1), String::from("a")),
+            Symbol::Name(Position::at(1, 28), String::from("d")),
+            Symbol::RParen(Position::at(1, 29)),
+        ],
+        lex("(a #;(b #;c '(x)) #; #; 1 e d)").unwrap()
+    );
+    assert_matches!(lex("(a #;)"), Err(_));
+    assert_matches!(lex("#; "), Err(_));
+}
+
+#[test]
+fn string_escapes() {
+    assert_eq!(
+        vec![Symbol::Primitive(
+            Position::at(1, 0),
+            Literal::String(String::from("a\n\t\r\\\"\0 A λ"))
+        )],
+        lex(r#""a\n\t\r\\\"\0 \x41; \u{3bb}""#).unwrap()
+    );
+    assert_matches!(lex(r#""\q""#), Err(_));
+    assert_matches!(lex(r#""\x41""#), Err(_));
+    assert_matches!(lex(r#""\u{d800}""#), Err(_));
+}
+
+#[test]
+fn raw_string() {
+    assert_eq!(
+        vec![
+            Symbol::Primitive(Position::at(1, 0), Literal::String(String::from("\\n"))),
+            Symbol::Primitive(
+                Position::at(1, 7),
+                Literal::String(String::from("say \"hi\"#"))
+            ),
+        ],
+        lex(r###"#r"\n" #r##"say "hi"#"##"###).unwrap()
+    );
+    assert!(lex("#r\"abc").unwrap_err().is_eof());
+}
+
+#[test]
+fn char_literals() {
+    assert_eq!(
+        vec![
+            Symbol::LParen(Position::at(1, 0)),
+            Symbol::Primitive(Position::at(1, 1), Literal::Char('a')),
+            Symbol::Primitive(Position::at(1, 5), Literal::Char(' ')),
+            Symbol::Primitive(Position::at(1, 13), Literal::Char('\n')),
+            Symbol::Primitive(Position::at(1, 23), Literal::Char('A')),
+            Symbol::Primitive(Position::at(1, 29), Literal::Char('(')),
+            Symbol::RParen(Position::at(1, 33)),
+        ],
+        lex("(#\\a #\\space #\\newline #\\x41 #\\( )").unwrap()
+    );
+    assert_matches!(lex("#\\spacey"), Err(_));
+    assert!(lex("#\\").unwrap_err().is_eof());
+}
+
+#[test]
+fn name_between_bars() {
+    assert_eq!(
+        vec![
+            Symbol::Name(Position::at(1, 0), String::from("tmp,2")),
+            Symbol::Name(Position::at(1, 8), String::from("a |b\\")),
+        ],
+        lex("|tmp,2| |a \\|b\\\\|").unwrap()
+    );
+    assert!(lex("|open").unwrap_err().is_eof());
+}
@@ -13,9 +13,11 @@ pub struct Position {
     character: usize,
 }
 
+/// Positions are displayed as `line:column`, where the column is 1-indexed
+/// like in editors, unlike the character number.
 impl fmt::Display for Position {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}:{}", self.line, self.character)
+        write!(f, "{}:{}", self.line, self.character + 1)
     }
 }
 
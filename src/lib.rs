@@ -4,7 +4,73 @@ extern crate assert_matches;
 
 pub mod convert;
 pub mod datastructure;
+pub mod diagnostics;
+mod error;
+mod interpreter;
 pub mod lexer;
 pub mod parser;
 pub mod stdlib;
 pub mod vm;
+
+#[cfg(test)]
+mod tests;
+
+pub use error::Error;
+pub use interpreter::Interpreter;
+
+use datastructure::Item;
+use vm::Machine;
+
+/// Evaluates every expression of the source code on the machine, and
+/// returns the value of the last one. Functions are returned as their name.
+///
+/// # Example
+///
+/// ```
+/// use minilisp::datastructure::{Item, Number};
+/// use minilisp::vm::Machine;
+///
+/// let mut machine = Machine::default();
+/// let result = minilisp::run("(def x 20) (+ x 22)", &mut machine).unwrap();
+/// assert_eq!(result, Item::Number(Number::Integer(42)));
+/// ```
+pub fn run(source: &str, machine: &mut Machine) -> Result<Item, Error> {
+    let mut symbols = lexer::lex(source)?;
+    let ast = parser::parse(&mut symbols)?;
+    let mut result = Item::None;
+    for (item, position) in convert::convert_located(ast) {
+        // Errors outside of any list, e.g. of a bare name, are located at
+        // the expression itself
+        let located = |error: vm::VMError| match position {
+            Some(position) if error.position().is_none() => error.with_position(position),
+            _ => error,
+        };
+        let item = machine.expand(item).map_err(located)?;
+        result = machine.eval(item).map_err(located)?.into();
+    }
+    Ok(result)
+}
+
+/// Expands every macro use within the source code, without evaluating it,
+/// and returns the expanded expressions. The macros defined by the source
+/// code can be used by the expressions following their definition.
+///
+/// # Example
+///
+/// ```
+/// use minilisp::vm::Machine;
+///
+/// let mut machine = Machine::default();
+/// let source = "(defmacro twice (x) `(begin ,x ,x)) (twice (print 1))";
+/// let items = minilisp::expand(source, &mut machine).unwrap();
+/// assert_eq!(items[1].to_string(), "(begin (print 1) (print 1))");
+/// ```
+pub fn expand(source: &str, machine: &mut Machine) -> Result<Vec<Item>, Error> {
+    let mut symbols = lexer::lex(source)?;
+    let ast = parser::parse(&mut symbols)?;
+    let mut items = Vec::new();
+    for item in convert::convert(ast) {
+        items.push(machine.expand(item)?);
+    }
+    Ok(items)
+}
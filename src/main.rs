@@ -1,21 +1,43 @@
-use minilisp::{convert, lexer, parser, stdlib, vm};
+use minilisp::diagnostics::Diagnostic;
+use minilisp::{vm, Error, Interpreter};
 use rustyline::Editor;
-use std::{env, fs};
+use std::{env, fs, process};
+
+/// The ways evaluating a piece of source code can fail.
+enum Failure {
+    /// The input ended in the middle of an expression. Contains the
+    /// rendered diagnostic.
+    Incomplete(String),
+    /// The error has already been reported.
+    Reported,
+}
 
 fn eval_files(files: Vec<String>) {
-    let mut machine = vm::Machine::default();
+    let mut interpreter = Interpreter::new();
     for file in files.iter() {
-        let content = fs::read_to_string(file);
-        assert!(content.is_ok(), "Could not read file '{}'", file);
-        let result = eval(content.unwrap(), &mut machine);
-        assert!(result.is_ok(), "Could not evaluate file '{}'", file);
-        println!("{}", result.unwrap());
+        let content = match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(err) => {
+                eprintln!("error: Could not read file '{}': {}", file, err);
+                process::exit(1);
+            }
+        };
+        match eval(&content, file, &mut interpreter) {
+            Ok(result) => println!("{}", result),
+            Err(Failure::Incomplete(diagnostic)) => {
+                eprint!("{}", diagnostic);
+                process::exit(1);
+            }
+            Err(Failure::Reported) => process::exit(1),
+        }
     }
 }
 
 fn interactive() {
     let mut rl = Editor::<()>::new();
-    let mut machine = vm::Machine::default();
+    let mut interpreter = Interpreter::new();
+    let handle = interpreter.machine().interrupt_handle();
+    ctrlc::set_handler(move || handle.interrupt()).expect("Could not handle Ctrl-C");
 
     'repl: loop {
         let readline = rl.readline(">> ");
@@ -24,18 +46,19 @@ fn interactive() {
             Ok(l) => {
                 line = l.clone();
                 loop {
-                    match eval(line.clone(), &mut machine) {
+                    match eval(&line, "<repl>", &mut interpreter) {
                         Ok(result) => {
                             println!("{}", result);
                             break;
                         }
-                        Err(_) => match rl.readline(".. ") {
+                        Err(Failure::Reported) => break,
+                        Err(Failure::Incomplete(diagnostic)) => match rl.readline(".. ") {
                             Ok(l) => {
                                 line.push('\n');
                                 line.push_str(&l);
                             }
                             Err(_) => {
-                                println!("An error occurred");
+                                eprint!("{}", diagnostic);
                                 break 'repl;
                             }
                         },
@@ -51,38 +74,30 @@ fn interactive() {
     }
 }
 
-fn eval(input: String, machine: &mut vm::Machine) -> Result<String, parser::error::ParserError> {
-    let data = convert::convert(parser::parse(&mut lexer::lex(&input).unwrap())?);
-
-    Ok(data
-        .into_iter()
-        .map(|d| {
-            let answer = machine.eval(d.clone());
-            let answer = match answer {
-                Ok(res) => res,
-                #[allow(unused_mut)]
-                Err(mut err) => {
-                    #[cfg(feature = "vm-debug")]
-                    {
-                        let bt = err.backtrace_mut();
-                        bt.resolve();
-                        eprintln!("{:?}", bt);
-                    }
-                    eprintln!(
-                        "The VM failed with the following error: \"{}\"",
-                        err.message()
-                    );
-                    panic!();
+fn eval(input: &str, source: &str, interpreter: &mut Interpreter) -> Result<String, Failure> {
+    match interpreter.eval_str(input) {
+        Ok(result) => Ok(format!("{}", result)),
+        Err(err) if err.is_eof() => Err(Failure::Incomplete(
+            Diagnostic::from(&err).render(source, input),
+        )),
+        Err(Error::VM(ref err)) if err.kind() == vm::VMErrorKind::Interrupted => {
+            eprintln!("Interrupted");
+            Err(Failure::Reported)
+        }
+        #[allow(unused_mut)]
+        Err(mut err) => {
+            #[cfg(feature = "vm-debug")]
+            {
+                if let Error::VM(ref mut err) = err {
+                    let bt = err.backtrace_mut();
+                    bt.resolve();
+                    eprintln!("{:?}", bt);
                 }
-            };
-            if let stdlib::EnvItem::Data(a) = answer {
-                format!("{}", a)
-            } else {
-                format!("{:?}", answer)
             }
-        })
-        .collect::<Vec<String>>()
-        .join("\n"))
+            eprint!("{}", Diagnostic::from(&err).render(source, input));
+            Err(Failure::Reported)
+        }
+    }
 }
 
 fn main() {
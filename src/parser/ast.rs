@@ -3,6 +3,7 @@ use super::*;
 use std::{iter::Peekable, slice::Iter};
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod tests;
 
 #[derive(Debug)]
@@ -27,6 +28,9 @@ pub enum Compound {
 #[derive(Debug, PartialEq)]
 pub enum Expression {
     QuoteExpression(Box<Expression>),
+    QuasiquoteExpression(Box<Expression>),
+    UnquoteExpression(Box<Expression>),
+    UnquoteSplicingExpression(Box<Expression>),
     List(List),
     Name(Position, String),
     Primitive(Position, Literal),
@@ -39,6 +43,13 @@ pub struct List {
     right: Position,
 }
 
+impl List {
+    /// Returns the position of the left parenthesis.
+    pub fn position(&self) -> Position {
+        self.left
+    }
+}
+
 type Queue<'a> = Peekable<Iter<'a, Symbol>>;
 
 pub trait Parsable {
@@ -73,6 +84,24 @@ impl Parsable for Expression {
                     input,
                 )?)))
             }
+            Some(Symbol::Quasiquote(_)) => {
+                input.next();
+                Ok(Expression::QuasiquoteExpression(Box::new(
+                    Expression::parse(input)?,
+                )))
+            }
+            Some(Symbol::Unquote(_)) => {
+                input.next();
+                Ok(Expression::UnquoteExpression(Box::new(Expression::parse(
+                    input,
+                )?)))
+            }
+            Some(Symbol::UnquoteSplicing(_)) => {
+                input.next();
+                Ok(Expression::UnquoteSplicingExpression(Box::new(
+                    Expression::parse(input)?,
+                )))
+            }
             Some(Symbol::LParen(_)) => Ok(Expression::List(List::parse(input)?)),
             Some(Symbol::Name(p, n)) => {
                 input.next();
@@ -84,11 +113,14 @@ impl Parsable for Expression {
             }
             Some(s) => Err(error::ParserError::new(
                 s.position(),
-                format!("Expected: ', (, name, or primitive, found: {:?}", s),
+                format!(
+                    "Expected: ', `, ,, ,@, (, name, or primitive, found: {:?}",
+                    s
+                ),
             )),
-            None => Err(error::ParserError::new(
+            None => Err(error::ParserError::unexpected_eof(
                 Position::at(0, 0), // TODO: Read previous Symbol
-                String::from("Expected: ', (, name, or primitive, found: EOF"),
+                String::from("Expected: ', `, ,, ,@, (, name, or primitive, found: EOF"),
             )),
         }
     }
@@ -107,7 +139,7 @@ impl Parsable for List {
                 ))
             }
             None => {
-                return Err(error::ParserError::new(
+                return Err(error::ParserError::unexpected_eof(
                     Position::at(0, 0), // TODO: Read previous Symbol
                     String::from("Expected: (, found: EOF"),
                 ));
@@ -116,7 +148,7 @@ impl Parsable for List {
         let content = Box::new(match input.peek() {
             Some(_) => Compound::parse(input)?,
             None => {
-                return Err(error::ParserError::new(
+                return Err(error::ParserError::unexpected_eof(
                     Position::at(0, 0), // TODO: Read previous Symbol
                     String::from("Expected: expression, or ), found: EOF"),
                 ));
@@ -131,7 +163,7 @@ impl Parsable for List {
                 ));
             }
             None => {
-                return Err(error::ParserError::new(
+                return Err(error::ParserError::unexpected_eof(
                     Position::at(0, 0), // TODO: Read previous Symbol
                     String::from("Expected: ), found: EOF"),
                 ));
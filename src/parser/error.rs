@@ -1,13 +1,50 @@
 use super::super::lexer::Position;
+use std::fmt;
 
 #[derive(Debug, PartialEq)]
 pub struct ParserError {
     position: Position,
     message: String,
+    eof: bool,
 }
 
 impl ParserError {
     pub fn new(position: Position, message: String) -> ParserError {
-        ParserError { position, message }
+        ParserError {
+            position,
+            message,
+            eof: false,
+        }
+    }
+
+    /// Generates an error for input, which ended in the middle of an
+    /// expression.
+    pub fn unexpected_eof(position: Position, message: String) -> ParserError {
+        ParserError {
+            eof: true,
+            ..ParserError::new(position, message)
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns true if the error was caused by the input ending too early,
+    /// meaning that more input could make it valid.
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
     }
 }
+
+impl std::error::Error for ParserError {}
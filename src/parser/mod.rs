@@ -8,6 +8,9 @@
 //! COMPOUND ::= EXPRESSION COMPOUND
 //!            | λ
 //! EXPRESSION ::= Quote EXPRESSION
+//!              | Quasiquote EXPRESSION
+//!              | Unquote EXPRESSION
+//!              | UnquoteSplicing EXPRESSION
 //!              | LIST
 //!              | PRIMITIVE
 //!              | Name
@@ -28,6 +31,8 @@ pub mod error;
 /// Parses a `Vec<lexer::Symbol>` into an `parser::ast::AST`
 ///
 /// TODO: Add an example, and explanation of the AST
+// The signature is part of the public API, so it keeps taking a `Vec`
+#[allow(clippy::ptr_arg)]
 pub fn parse(symbols: &mut Vec<Symbol>) -> Result<ast::AST, error::ParserError> {
     let ast = ast::AST::parse(&mut symbols.iter().peekable())?;
     if let ast::Compound::None = *ast.root {
@@ -1,4 +1,6 @@
+use super::super::lexer::Position;
 use super::vm::VMError;
+use std::fmt;
 
 #[cfg(feature = "vm-debug")]
 use backtrace::Backtrace;
@@ -7,6 +9,8 @@ use backtrace::Backtrace;
 pub struct EvalError {
     pub code: EvalErrorCode,
     pub message: String,
+    /// The position in the source code of the expression that failed.
+    pub position: Option<Position>,
     #[cfg(feature = "vm-debug")]
     pub backtrace: Backtrace,
 }
@@ -19,6 +23,7 @@ impl EvalError {
         EvalError {
             code,
             message: message.into(),
+            position: None,
             #[cfg(feature = "vm-debug")]
             backtrace: Backtrace::new_unresolved(),
         }
@@ -29,14 +34,23 @@ impl From<VMError> for EvalError {
     fn from(error: VMError) -> EvalError {
         EvalError {
             message: error.message().into(),
-            code: EvalErrorCode::E0011,
+            code: error.code().unwrap_or(EvalErrorCode::E0011),
+            position: error.position(),
             #[cfg(feature = "vm-debug")]
             backtrace: error.backtrace_own(),
         }
     }
 }
 
-#[derive(Debug)]
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum EvalErrorCode {
     /// Data is not a function
     E0001,
@@ -62,6 +76,18 @@ pub enum EvalErrorCode {
     E0011,
     /// Incompatible type
     E0012,
+    /// Arithmetic fault, such as division by zero
+    E0013,
+    /// Special form is malformed
+    E0014,
+    /// Value is out of range, such as an index past the end of a string
+    E0015,
+}
+
+impl fmt::Display for EvalErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
 }
 
 pub fn mismatch_arguments(method: &str, expected: usize, found: usize) -> EvalError {
@@ -71,6 +97,7 @@ pub fn mismatch_arguments(method: &str, expected: usize, found: usize) -> EvalEr
             "Wrong amount of arguments for '{}'. Expexted {}, found {}",
             method, expected, found
         ),
+        position: None,
         #[cfg(feature = "vm-debug")]
         backtrace: Backtrace::new_unresolved(),
     }
@@ -80,6 +107,27 @@ pub fn unparseable_arguments(method: &str) -> EvalError {
     EvalError {
         code: EvalErrorCode::E0010,
         message: format!("Could not parse arguments for '{}'", method),
+        position: None,
+        #[cfg(feature = "vm-debug")]
+        backtrace: Backtrace::new_unresolved(),
+    }
+}
+
+pub fn division_by_zero(method: &str) -> EvalError {
+    EvalError {
+        code: EvalErrorCode::E0013,
+        message: format!("Division by zero in '{}'", method),
+        position: None,
+        #[cfg(feature = "vm-debug")]
+        backtrace: Backtrace::new_unresolved(),
+    }
+}
+
+pub fn out_of_range(method: &str, value: i64) -> EvalError {
+    EvalError {
+        code: EvalErrorCode::E0015,
+        message: format!("The value {} is out of range for '{}'", value, method),
+        position: None,
         #[cfg(feature = "vm-debug")]
         backtrace: Backtrace::new_unresolved(),
     }
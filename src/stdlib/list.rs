@@ -1,76 +1,82 @@
 use super::*;
+use vm::native::native_fn;
 
-pub fn cons(machine: &mut Machine) -> FunctionOutput {
-    let car_arg = machine.lookup("car")?;
-    let cdr_arg = machine.lookup("cdr")?;
-
-    let car;
-    if let EnvItem::Data(d) = car_arg {
-        car = d;
-    } else {
-        return Err(error::EvalError::new(
-            error::EvalErrorCode::E0010,
-            "Could not parse the car element",
-        ));
-    }
-    let cdr;
-    if let EnvItem::Data(d) = cdr_arg {
-        cdr = d;
-    } else {
-        return Err(error::EvalError::new(
-            error::EvalErrorCode::E0010,
-            "Could not parse the cdr element",
-        ));
-    }
-    Ok(EnvItem::Data(Item::Cons(Cons::new(car, cdr))))
+pub fn cons(car: Item, cdr: Item) -> Result<Item, error::EvalError> {
+    Ok(Item::Cons(Cons::new(car, cdr)))
 }
 
 pub fn cons_env() -> EnvItem {
-    EnvItem::Function(
-        "cons".into(),
-        Rc::new(cons),
-        Parameters::Individual(vec!["car".into(), "cdr".into()]),
-    )
+    native_fn("cons", cons)
 }
 
-pub fn car(machine: &mut Machine) -> FunctionOutput {
-    let list = machine.lookup("list")?;
-
-    if let EnvItem::Data(Item::Cons(list)) = list {
-        Ok(EnvItem::Data(list.car().clone()))
-    } else {
-        Err(error::EvalError::new(
-            error::EvalErrorCode::E0012,
-            "The 'car' function requires a list",
-        ))
-    }
+pub fn car(list: Cons) -> Result<Item, error::EvalError> {
+    Ok(list.car().clone())
 }
 
 pub fn car_env() -> EnvItem {
-    EnvItem::Function(
-        "car".into(),
-        Rc::new(car),
-        Parameters::Individual(vec!["list".into()]),
-    )
+    native_fn("car", car)
 }
 
-pub fn cdr(machine: &mut Machine) -> FunctionOutput {
-    let list = machine.lookup("list")?;
+pub fn cdr(list: Cons) -> Result<Item, error::EvalError> {
+    Ok(list.cdr())
+}
 
-    if let EnvItem::Data(Item::Cons(list)) = list {
-        Ok(EnvItem::Data(list.cdr()))
-    } else {
-        Err(error::EvalError::new(
-            error::EvalErrorCode::E0012,
-            "The 'cdr' function requires a list",
-        ))
+pub fn cdr_env() -> EnvItem {
+    native_fn("cdr", cdr)
+}
+
+/// Joins the lists, where the last argument becomes the tail of the result,
+/// so it does not have to be a list.
+pub fn append(machine: &mut Machine) -> FunctionOutput {
+    let mut lists = match machine.lookup("lists")? {
+        EnvItem::VariableBinding(lists) => lists,
+        _ => return Err(error::unparseable_arguments("append")),
+    };
+    let tail = match lists.pop() {
+        Some(EnvItem::Data(tail)) => tail,
+        Some(tail) => {
+            return Err(error::EvalError::new(
+                error::EvalErrorCode::E0012,
+                format!("Cannot append to: {}", tail),
+            ))
+        }
+        None => return Ok(EnvItem::Data(Item::None)),
+    };
+    let mut elements = Vec::new();
+    for list in lists {
+        match list {
+            EnvItem::Data(Item::None) => {}
+            EnvItem::Data(Item::Cons(list)) if list.is_null_terminated() => {
+                elements.extend(list.elements().iter().cloned())
+            }
+            list => {
+                return Err(error::EvalError::new(
+                    error::EvalErrorCode::E0012,
+                    format!("Expected a list, found: {}", list),
+                ))
+            }
+        }
     }
+    let result = match tail {
+        tail if elements.is_empty() => tail,
+        Item::None => Item::Cons(Cons::from(elements)),
+        Item::Cons(rest) if rest.is_null_terminated() => {
+            elements.extend(rest.elements().iter().cloned());
+            Item::Cons(Cons::from(elements))
+        }
+        tail => elements
+            .into_iter()
+            .rev()
+            .fold(tail, |rest, element| Item::Cons(Cons::new(element, rest))),
+    };
+    Ok(EnvItem::Data(result))
 }
 
-pub fn cdr_env() -> EnvItem {
+pub fn append_env() -> EnvItem {
     EnvItem::Function(
-        "cdr".into(),
-        Rc::new(cdr),
-        Parameters::Individual(vec!["list".into()]),
+        "append".into(),
+        Rc::new(append),
+        Parameters::All("lists".into()),
+        None,
     )
 }
@@ -2,7 +2,7 @@ use super::*;
 
 fn get_number(item: &EnvItem) -> Result<Number, error::EvalError> {
     match item {
-        EnvItem::Data(Item::Number(n)) => Ok(*n),
+        EnvItem::Data(Item::Number(n)) => Ok(n.clone()),
         _ => Err(error::EvalError::new(
             error::EvalErrorCode::E0010,
             "Addition: Only supports numbers for now",
@@ -21,7 +21,7 @@ fn get_args(machine: &mut Machine, args: &str) -> Result<Vec<EnvItem>, error::Ev
 
 pub fn addition(machine: &mut Machine) -> FunctionOutput {
     let args = get_args(machine, "args")?;
-    let mut sum = 0;
+    let mut sum = Number::Integer(0);
     for item in args.iter() {
         sum += get_number(item)?;
     }
@@ -33,13 +33,17 @@ pub fn addition_env() -> EnvItem {
         "addition".into(),
         Rc::new(addition),
         Parameters::All("args".into()),
+        None,
     )
 }
 
 pub fn subtraction(machine: &mut Machine) -> FunctionOutput {
     let args = get_args(machine, "args")?;
     let mut args = args.iter();
-    let mut sum = get_number(args.next().unwrap())?;
+    let mut sum = match args.next() {
+        Some(first) => get_number(first)?,
+        None => return Err(error::mismatch_arguments("subtraction", 1, 0)),
+    };
     for item in args {
         sum -= get_number(item)?;
     }
@@ -51,12 +55,13 @@ pub fn subtration_env() -> EnvItem {
         "subtraction".into(),
         Rc::new(subtraction),
         Parameters::All("args".into()),
+        None,
     )
 }
 
 pub fn multiplication(machine: &mut Machine) -> FunctionOutput {
     let args = get_args(machine, "args")?;
-    let mut sum = 1;
+    let mut sum = Number::Integer(1);
     for item in args.iter() {
         sum *= get_number(item)?;
     }
@@ -68,6 +73,7 @@ pub fn multiplication_env() -> EnvItem {
         "multiplication".into(),
         Rc::new(multiplication),
         Parameters::All("args".into()),
+        None,
     )
 }
 
@@ -75,9 +81,10 @@ pub fn division(machine: &mut Machine) -> FunctionOutput {
     let dividend = machine.lookup("dividend")?;
     let divisor = machine.lookup("divisor")?;
 
-    Ok(Output::Data(Item::Number(
-        get_number(&dividend)? / get_number(&divisor)?,
-    )))
+    match get_number(&dividend)?.checked_div(get_number(&divisor)?) {
+        Some(quotient) => Ok(Output::Data(Item::Number(quotient))),
+        None => Err(error::division_by_zero("division")),
+    }
 }
 
 pub fn division_env() -> EnvItem {
@@ -85,6 +92,7 @@ pub fn division_env() -> EnvItem {
         "division".into(),
         Rc::new(division),
         Parameters::Individual(vec!["dividend".into(), "divisor".into()]),
+        None,
     )
 }
 
@@ -92,9 +100,10 @@ pub fn modulo(machine: &mut Machine) -> FunctionOutput {
     let dividend = machine.lookup("dividend")?;
     let divisor = machine.lookup("divisor")?;
 
-    Ok(Output::Data(Item::Number(
-        get_number(&dividend)? % get_number(&divisor)?,
-    )))
+    match get_number(&dividend)?.checked_rem(get_number(&divisor)?) {
+        Some(remainder) => Ok(Output::Data(Item::Number(remainder))),
+        None => Err(error::division_by_zero("modulo")),
+    }
 }
 
 pub fn modulo_env() -> EnvItem {
@@ -102,6 +111,7 @@ pub fn modulo_env() -> EnvItem {
         "modulo".into(),
         Rc::new(modulo),
         Parameters::Individual(vec!["dividend".into(), "divisor".into()]),
+        None,
     )
 }
 
@@ -119,6 +129,7 @@ pub fn less_than_env() -> EnvItem {
         "less_than".into(),
         Rc::new(less_than),
         Parameters::Individual(vec!["left_comparand".into(), "right_comparand".into()]),
+        None,
     )
 }
 
@@ -136,6 +147,7 @@ pub fn greater_than_env() -> EnvItem {
         "greater_than".into(),
         Rc::new(greater_than),
         Parameters::Individual(vec!["left_comparand".into(), "right_comparand".into()]),
+        None,
     )
 }
 
@@ -153,5 +165,6 @@ pub fn equals_env() -> EnvItem {
         "equals".into(),
         Rc::new(equals),
         Parameters::Individual(vec!["left_comparand".into(), "right_comparand".into()]),
+        None,
     )
 }
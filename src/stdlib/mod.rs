@@ -4,7 +4,7 @@
 //! This function has the job of evaluating the data structures into actual code.
 
 pub use super::datastructure::{
-    Cons, EnvItem, Environment, FunctionOutput, Item, Number, Output, Parameters,
+    Cons, EnvItem, Environment, Foreign, FunctionOutput, Item, Number, Output, Parameters,
 };
 use super::vm;
 pub use super::vm::Machine;
@@ -16,6 +16,7 @@ pub mod error;
 //pub mod lambda;
 pub mod list;
 pub mod math;
+pub mod string;
 pub mod types;
 
 //pub use eval::eval;
@@ -43,13 +44,21 @@ pub fn stdlib() -> Environment {
     env.assign("cons", list::cons_env());
     env.assign("car", list::car_env());
     env.assign("cdr", list::cdr_env());
+    env.assign("append", list::append_env());
+    env.assign("char->integer", string::char_to_integer_env());
+    env.assign("integer->char", string::integer_to_char_env());
+    env.assign("string-ref", string::string_ref_env());
     env.assign("number?", types::is_number_env());
     env.assign("string?", types::is_string_env());
+    env.assign("char?", types::is_char_env());
     env.assign("boolean?", types::is_boolean_env());
     env.assign("name?", types::is_name_env());
     env.assign("list?", types::is_list_env());
     env.assign("none?", types::is_none_env());
     env.assign("function?", types::is_function_env());
+    env.assign("foreign?", types::is_foreign_env());
+    env.assign("macroexpand", macroexpand_env());
+    env.assign("macroexpand-1", macroexpand_1_env());
 
     env
 }
@@ -76,9 +85,41 @@ pub fn def_env() -> EnvItem {
         "define".into(),
         Rc::new(def),
         Parameters::Individual(vec!["name".into(), "value".into()]),
+        None,
     )
 }
 
 pub fn quote(machine: &mut Machine) -> FunctionOutput {
     Ok(machine.lookup("value")?)
 }
+
+/// Expands every macro use within the form, as it would be expanded before
+/// being evaluated.
+pub fn macroexpand(machine: &mut Machine) -> FunctionOutput {
+    let form = machine.lookup("form")?;
+    Ok(EnvItem::Data(machine.expand(form.into())?))
+}
+
+pub fn macroexpand_env() -> EnvItem {
+    EnvItem::Function(
+        "macroexpand".into(),
+        Rc::new(macroexpand),
+        Parameters::Individual(vec!["form".into()]),
+        None,
+    )
+}
+
+/// Expands the form once, if it is a macro use.
+pub fn macroexpand_1(machine: &mut Machine) -> FunctionOutput {
+    let form = machine.lookup("form")?;
+    Ok(EnvItem::Data(machine.expand_once(form.into())?))
+}
+
+pub fn macroexpand_1_env() -> EnvItem {
+    EnvItem::Function(
+        "macroexpand-1".into(),
+        Rc::new(macroexpand_1),
+        Parameters::Individual(vec!["form".into()]),
+        None,
+    )
+}
@@ -0,0 +1,37 @@
+use super::*;
+use std::convert::TryFrom;
+use vm::native::native_fn;
+
+pub fn char_to_integer(c: char) -> Result<i64, error::EvalError> {
+    Ok(i64::from(u32::from(c)))
+}
+
+pub fn char_to_integer_env() -> EnvItem {
+    native_fn("char->integer", char_to_integer)
+}
+
+/// Returns the character of the code point, which fails for surrogates and
+/// values past the last code point.
+pub fn integer_to_char(code: i64) -> Result<char, error::EvalError> {
+    u32::try_from(code)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| error::out_of_range("integer->char", code))
+}
+
+pub fn integer_to_char_env() -> EnvItem {
+    native_fn("integer->char", integer_to_char)
+}
+
+/// Returns the character at the index, counted in characters rather than
+/// bytes.
+pub fn string_ref(string: String, index: i64) -> Result<char, error::EvalError> {
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| string.chars().nth(i))
+        .ok_or_else(|| error::out_of_range("string-ref", index))
+}
+
+pub fn string_ref_env() -> EnvItem {
+    native_fn("string-ref", string_ref)
+}
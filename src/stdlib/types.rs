@@ -15,6 +15,7 @@ pub fn is_number_env() -> EnvItem {
         "number?".into(),
         Rc::new(is_number),
         Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
@@ -33,6 +34,26 @@ pub fn is_string_env() -> EnvItem {
         "string?".into(),
         Rc::new(is_string),
         Parameters::Individual(vec!["value".into()]),
+        None,
+    )
+}
+
+pub fn is_char(machine: &mut Machine) -> FunctionOutput {
+    let value = machine.lookup("value")?;
+
+    if let EnvItem::Data(Item::Char(_)) = value {
+        Ok(EnvItem::Data(Item::Boolean(true)))
+    } else {
+        Ok(EnvItem::Data(Item::Boolean(false)))
+    }
+}
+
+pub fn is_char_env() -> EnvItem {
+    EnvItem::Function(
+        "char?".into(),
+        Rc::new(is_char),
+        Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
@@ -51,6 +72,7 @@ pub fn is_boolean_env() -> EnvItem {
         "boolean?".into(),
         Rc::new(is_boolean),
         Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
@@ -69,6 +91,7 @@ pub fn is_name_env() -> EnvItem {
         "name?".into(),
         Rc::new(is_name),
         Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
@@ -87,6 +110,7 @@ pub fn is_list_env() -> EnvItem {
         "list?".into(),
         Rc::new(is_list),
         Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
@@ -105,13 +129,14 @@ pub fn is_none_env() -> EnvItem {
         "none?".into(),
         Rc::new(is_none),
         Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
 pub fn is_function(machine: &mut Machine) -> FunctionOutput {
     let value = machine.lookup("value")?;
 
-    if let EnvItem::Function(_, _, _) = value {
+    if let EnvItem::Function(_, _, _, _) = value {
         Ok(EnvItem::Data(Item::Boolean(true)))
     } else {
         Ok(EnvItem::Data(Item::Boolean(false)))
@@ -123,5 +148,25 @@ pub fn is_function_env() -> EnvItem {
         "function?".into(),
         Rc::new(is_function),
         Parameters::Individual(vec!["value".into()]),
+        None,
+    )
+}
+
+pub fn is_foreign(machine: &mut Machine) -> FunctionOutput {
+    let value = machine.lookup("value")?;
+
+    if let EnvItem::Data(Item::Foreign(_)) = value {
+        Ok(EnvItem::Data(Item::Boolean(true)))
+    } else {
+        Ok(EnvItem::Data(Item::Boolean(false)))
+    }
+}
+
+pub fn is_foreign_env() -> EnvItem {
+    EnvItem::Function(
+        "foreign?".into(),
+        Rc::new(is_foreign),
+        Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
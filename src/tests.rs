@@ -0,0 +1,94 @@
+use super::datastructure::{Item, Number};
+use super::stdlib::error::EvalErrorCode;
+use super::vm::Machine;
+use super::*;
+
+#[test]
+fn run_returns_last_value() {
+    let mut machine = Machine::default();
+    assert_eq!(
+        run("(def x 2) (* x 3)", &mut machine).unwrap(),
+        Item::Number(Number::Integer(6))
+    );
+    assert_eq!(run("", &mut machine).unwrap(), Item::None);
+}
+
+#[test]
+fn run_returns_function_name() {
+    let mut machine = Machine::default();
+    assert_eq!(
+        run("(def f car) f", &mut machine).unwrap(),
+        Item::Name("car".into())
+    );
+}
+
+#[test]
+fn run_reports_every_stage() {
+    let mut machine = Machine::default();
+    assert_matches!(run("\"open", &mut machine), Err(Error::Lexer(_)));
+    assert_matches!(run("(+ 1", &mut machine), Err(Error::Parser(ref e)) if e.is_eof());
+    let error = run("(car 1 2)", &mut machine).unwrap_err();
+    assert_matches!(error, Error::VM(_));
+    assert_eq!(error.code(), Some(EvalErrorCode::E0006));
+    assert!(error.position().is_some());
+}
+
+#[test]
+fn error_has_source() {
+    let error = run("(+ 1", &mut Machine::default()).unwrap_err();
+    assert!(std::error::Error::source(&error).is_some());
+    assert_eq!(
+        error.to_string(),
+        format!("{}: {}", error.position().unwrap(), error.message())
+    );
+}
+
+#[test]
+fn printed_strings_read_back() {
+    let mut machine = Machine::default();
+    let string = run(r#""tab\there \"quoted\" \\ \x7f; \u{1F600}""#, &mut machine).unwrap();
+    assert_eq!(run(&string.to_string(), &mut machine).unwrap(), string);
+}
+
+#[test]
+fn char_builtins() {
+    let mut machine = Machine::default();
+    assert_eq!(
+        run("(char->integer (string-ref \"aλb\" 1))", &mut machine).unwrap(),
+        Item::Number(Number::Integer(0x3bb))
+    );
+    assert_eq!(
+        run("(integer->char 65)", &mut machine).unwrap(),
+        Item::Char('A')
+    );
+    assert_eq!(
+        run("(char? #\\space)", &mut machine).unwrap(),
+        Item::Boolean(true)
+    );
+    assert_eq!(
+        run("(char? \"a\")", &mut machine).unwrap(),
+        Item::Boolean(false)
+    );
+    let error = run("(string-ref \"ab\" 2)", &mut machine).unwrap_err();
+    assert_matches!(error, Error::VM(ref e) if e.code() == Some(EvalErrorCode::E0015));
+    let error = run("(integer->char 55296)", &mut machine).unwrap_err();
+    assert_matches!(error, Error::VM(ref e) if e.code() == Some(EvalErrorCode::E0015));
+}
+
+#[test]
+fn printed_chars_read_back() {
+    let mut machine = Machine::default();
+    for source in &["#\\a", "#\\space", "#\\x1", "#\\(", "#\\λ"] {
+        let c = run(source, &mut machine).unwrap();
+        assert_eq!(run(&c.to_string(), &mut machine).unwrap(), c);
+    }
+}
+
+#[test]
+fn unbound_names_are_located_at_the_name() {
+    let mut machine = Machine::default();
+    let error = run("(def x 1)\n  undefined-name", &mut machine).unwrap_err();
+    assert_eq!(error.position(), Some(lexer::Position::at(2, 2)));
+    let error = run("(def x 1)\n(+ x\n   undefined-name)", &mut machine).unwrap_err();
+    assert_eq!(error.position(), Some(lexer::Position::at(3, 3)));
+}
@@ -1,11 +1,61 @@
-use super::super::stdlib::error::EvalError;
+use super::super::lexer::Position;
+use super::super::stdlib::error::{EvalError, EvalErrorCode};
+use super::syntax_rules::base;
+use std::fmt;
 
 #[cfg(feature = "vm-debug")]
 use backtrace::Backtrace;
 
+/// The different kinds of errors the machine can fail with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VMErrorKind {
+    /// An error in the program being evaluated.
+    Runtime,
+    /// The `max_stack_depth` limit of the machine was exceeded.
+    StackOverflow,
+    /// The `max_instructions` limit of the machine was exceeded.
+    InstructionLimit,
+    /// The `max_allocations` limit of the machine was exceeded.
+    AllocationLimit,
+    /// The machine was stopped through an `InterruptHandle`.
+    Interrupted,
+}
+
+/// An entry in the Lisp call stack, at the time an error occurred.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceFrame {
+    name: String,
+    position: Option<Position>,
+}
+
+impl TraceFrame {
+    /// Generates a frame of the call, where a name renamed by a macro is
+    /// shown as it was written.
+    pub fn new(name: String, position: Option<Position>) -> TraceFrame {
+        TraceFrame {
+            name: base(&name).into(),
+            position,
+        }
+    }
+
+    /// Returns the name of the called function.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the position of the call in the source code, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+}
+
 #[derive(Debug)]
 pub struct VMError {
+    kind: VMErrorKind,
     message: String,
+    code: Option<EvalErrorCode>,
+    position: Option<Position>,
+    trace: Vec<TraceFrame>,
     #[cfg(feature = "vm-debug")]
     backtrace: Backtrace,
 }
@@ -16,7 +66,11 @@ impl VMError {
         T: Into<String>,
     {
         VMError {
+            kind: VMErrorKind::Runtime,
             message: message.into(),
+            code: None,
+            position: None,
+            trace: Vec::new(),
             #[cfg(feature = "vm-debug")]
             backtrace: Backtrace::new_unresolved(),
         }
@@ -28,7 +82,11 @@ impl VMError {
         T: Into<String>,
     {
         VMError {
+            kind: VMErrorKind::Runtime,
             message: message.into(),
+            code: None,
+            position: None,
+            trace: Vec::new(),
             backtrace,
         }
     }
@@ -43,34 +101,142 @@ impl VMError {
 
     #[cfg(feature = "vm-debug")]
     pub fn eval_error(error: EvalError) -> VMError {
-        VMError::error_with_backtrace(
-            format!(
-                "Error occured while calling function. Reason: {}",
-                error.message
-            ),
-            error.backtrace,
-        )
+        VMError {
+            code: Some(error.code),
+            position: error.position,
+            ..VMError::error_with_backtrace(
+                format!(
+                    "Error occured while calling function. Reason: {}",
+                    error.message
+                ),
+                error.backtrace,
+            )
+        }
     }
 
     #[cfg(not(feature = "vm-debug"))]
     pub fn eval_error(error: EvalError) -> VMError {
-        VMError::error(format!(
-            "Error occured while calling function. Reason: {}",
-            error.message
-        ))
+        VMError {
+            code: Some(error.code),
+            position: error.position,
+            ..VMError::error(format!(
+                "Error occured while calling function. Reason: {}",
+                error.message
+            ))
+        }
     }
 
     pub fn wrong_parameter_len(expected: usize, found: usize) -> VMError {
-        VMError::error(format!(
-            "Wrong amount of arguments. Expexted {}, found {}",
-            expected, found
-        ))
+        VMError {
+            code: Some(EvalErrorCode::E0006),
+            ..VMError::error(format!(
+                "Wrong amount of arguments. Expexted {}, found {}",
+                expected, found
+            ))
+        }
+    }
+
+    pub fn unbound_name(name: &str) -> VMError {
+        VMError {
+            code: Some(EvalErrorCode::E0002),
+            ..VMError::error(format!("'{}' not found", base(name)))
+        }
+    }
+
+    pub fn set_unbound(name: &str) -> VMError {
+        VMError {
+            code: Some(EvalErrorCode::E0002),
+            ..VMError::error(format!("Cannot set '{}', as it is not bound", base(name)))
+        }
+    }
+
+    /// An error for a special form, which is not written correctly.
+    pub fn malformed(form: &str, message: &str) -> VMError {
+        VMError {
+            code: Some(EvalErrorCode::E0014),
+            ..VMError::error(format!("Malformed '{}': {}", form, message))
+        }
+    }
+
+    pub fn not_bound_to_function(name: &str) -> VMError {
+        VMError {
+            code: Some(EvalErrorCode::E0004),
+            ..VMError::error(format!("'{}' is not bound to a function", base(name)))
+        }
+    }
+
+    pub fn stack_overflow(limit: usize) -> VMError {
+        VMError {
+            kind: VMErrorKind::StackOverflow,
+            ..VMError::error(format!("Stack overflow. The limit is {} frames", limit))
+        }
+    }
+
+    pub fn instruction_limit(limit: usize) -> VMError {
+        VMError {
+            kind: VMErrorKind::InstructionLimit,
+            ..VMError::error(format!(
+                "Instruction limit exceeded. The limit is {} instructions",
+                limit
+            ))
+        }
+    }
+
+    pub fn allocation_limit(limit: usize) -> VMError {
+        VMError {
+            kind: VMErrorKind::AllocationLimit,
+            ..VMError::error(format!(
+                "Allocation limit exceeded. The limit is {} values",
+                limit
+            ))
+        }
+    }
+
+    pub fn interrupted() -> VMError {
+        VMError {
+            kind: VMErrorKind::Interrupted,
+            ..VMError::error("The evaluation was interrupted")
+        }
+    }
+
+    pub fn kind(&self) -> VMErrorKind {
+        self.kind
     }
 
     pub fn message(&self) -> &str {
         &self.message
     }
 
+    /// Returns the error located at the given position in the source code.
+    pub fn with_position(self, position: Position) -> VMError {
+        VMError {
+            position: Some(position),
+            ..self
+        }
+    }
+
+    /// Returns the position in the source code of the expression that
+    /// failed, if it is known.
+    pub fn position(&self) -> Option<Position> {
+        self.position
+    }
+
+    /// Returns the error with the given Lisp call stack.
+    pub fn with_trace(self, trace: Vec<TraceFrame>) -> VMError {
+        VMError { trace, ..self }
+    }
+
+    /// Returns the Lisp call stack at the time of the error, starting with
+    /// the outermost call.
+    pub fn trace(&self) -> &[TraceFrame] {
+        &self.trace
+    }
+
+    /// Returns the code of the `EvalError` that caused this error, if any.
+    pub fn code(&self) -> Option<EvalErrorCode> {
+        self.code
+    }
+
     #[cfg(feature = "vm-debug")]
     pub fn backtrace(&self) -> &Backtrace {
         &self.backtrace
@@ -86,3 +252,14 @@ impl VMError {
         self.backtrace
     }
 }
+
+impl fmt::Display for VMError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{}: {}", position, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for VMError {}
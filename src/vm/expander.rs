@@ -0,0 +1,344 @@
+//! This module expands the macros defined through `define-syntax` and
+//! `defmacro`, before the expanded code is evaluated by the machine.
+//!
+//! The expansion is only partially hygienic. Every name introduced by a
+//! template is renamed, so the names bound by the template cannot capture a
+//! name of the code using the macro. Once a form is fully expanded, the
+//! renamed names, which are not bound by a binding form of the expansion,
+//! get their original name back. They refer to the special forms and
+//! bindings visible where the macro is used, so a local binding of e.g. `+`
+//! around a use changes the meaning of a `+` in the template.
+
+use super::super::datastructure::{Cons, EnvItem, Item};
+use super::forms::gen_list;
+use super::syntax_rules::{self, base, is_renamed, Renamer, SyntaxRules};
+use super::{Machine, VMError, VMOutput};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+pub struct Expander {
+    macros: HashMap<String, SyntaxRules>,
+    /// The number of names renamed so far, used for generating new names.
+    renamed: usize,
+}
+
+impl Expander {
+    /// Returns true if the name refers to a macro.
+    pub fn is_macro(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+
+    /// Looks up the macro of the name, where a renamed name refers to the
+    /// macro of the name it was renamed from.
+    fn lookup(&self, name: &str) -> Option<&SyntaxRules> {
+        let mut name = name;
+        loop {
+            if let Some(syntax) = self.macros.get(name) {
+                return Some(syntax);
+            }
+            name = syntax_rules::original(name)?;
+        }
+    }
+
+    fn define_syntax(&mut self, list: &Cons) -> VMOutput<Item> {
+        let (name, spec) = match list.elements() {
+            [_, Item::Name(name), spec] if list.is_null_terminated() => (name.clone(), spec),
+            _ => {
+                return Err(VMError::malformed(
+                    "define-syntax",
+                    "expected (define-syntax name (syntax-rules ...))",
+                ))
+            }
+        };
+        let syntax = SyntaxRules::new(&name, spec)?;
+        self.macros.insert(name.clone(), syntax);
+        Ok(gen_list(vec![Item::Name("quote".into()), Item::Name(name)]))
+    }
+}
+
+impl Machine {
+    /// Expands every macro use within the item, and defines the macros of
+    /// its `define-syntax` forms, which are replaced by the quoted name, and
+    /// of its `defmacro` forms, which are kept.
+    pub(super) fn expand_all(&mut self, item: Item) -> VMOutput<Item> {
+        let item = self.expand_item(item)?;
+        Ok(resolve_bound(&item))
+    }
+
+    /// Expands the item once, if it is a macro use.
+    pub(super) fn expand_head(&mut self, item: Item) -> VMOutput<Item> {
+        match item {
+            Item::Cons(list) if list.is_null_terminated() => match self.expand_macro(&list)? {
+                Some(expansion) => Ok(resolve_bound(&expansion)),
+                None => Ok(Item::Cons(list)),
+            },
+            item => Ok(item),
+        }
+    }
+
+    /// Looks up the transformer of the `defmacro` macro of the name, where a
+    /// renamed name refers to the macro of the name it was renamed from.
+    fn transformer(&self, name: &str) -> Option<EnvItem> {
+        let mut name = name;
+        loop {
+            if let Some(transformer) = self.macros.get(name) {
+                return Some(transformer.clone());
+            }
+            name = syntax_rules::original(name)?;
+        }
+    }
+
+    /// Rewrites the list once, if it is the use of a macro.
+    fn expand_macro(&mut self, list: &Cons) -> VMOutput<Option<Item>> {
+        let name = match list.car() {
+            Item::Name(name) => name,
+            _ => return Ok(None),
+        };
+        let expansion = if let Some(syntax) = self.syntax.lookup(name).cloned() {
+            syntax.expand(list, &mut Renamer::new(&mut self.syntax.renamed))
+        } else if let Some(transformer) = self.transformer(name) {
+            self.call_transformer(transformer, &list.elements()[1..])
+        } else {
+            return Ok(None);
+        };
+        match expansion {
+            Ok(expansion) => Ok(Some(located(expansion, list))),
+            Err(error) => Err(at(error, list)),
+        }
+    }
+
+    fn expand_item(&mut self, item: Item) -> VMOutput<Item> {
+        let list = match item {
+            Item::Cons(list) if list.is_null_terminated() => list,
+            item => return Ok(item),
+        };
+        if let Some(expansion) = self.expand_macro(&list)? {
+            return self.expand_item(expansion);
+        }
+        let name = match list.car() {
+            Item::Name(name) => name.clone(),
+            _ => return self.expand_elements(list, 0),
+        };
+        match base(&name) {
+            "quote" => Ok(Item::Cons(list)),
+            "quasiquote" => self.expand_quasiquote(Item::Cons(list), 0),
+            "define-syntax" => self
+                .syntax
+                .define_syntax(&list)
+                .map_err(|error| at(error, &list)),
+            "lambda" => self.expand_elements(list, 2),
+            "defmacro" => {
+                let expanded = self.expand_elements(list.clone(), 3)?;
+                if let Item::Cons(form) = resolve_bound(&expanded) {
+                    // Later forms can use the macro before it is evaluated
+                    self.define_macro(&form, self.env.global())
+                        .map_err(|error| at(error, &list))?;
+                }
+                Ok(expanded)
+            }
+            "let" | "let*" | "letrec" => {
+                // Only the values of the bindings are expressions
+                let mut elements = list.elements().to_vec();
+                if let Some(Item::Cons(bindings)) = elements.get(1) {
+                    let mut expanded = Vec::with_capacity(bindings.len());
+                    for binding in bindings.iter() {
+                        expanded.push(match binding {
+                            Item::Cons(binding) => self.expand_elements(binding.clone(), 1)?,
+                            binding => binding.clone(),
+                        });
+                    }
+                    elements[1] = rebuilt(expanded, bindings);
+                }
+                for element in elements.iter_mut().skip(2) {
+                    *element = self.expand_item(element.clone())?;
+                }
+                Ok(rebuilt(elements, &list))
+            }
+            _ => self.expand_elements(list, 0),
+        }
+    }
+
+    /// Expands the elements of the list, except for the first `skip` ones.
+    fn expand_elements(&mut self, list: Cons, skip: usize) -> VMOutput<Item> {
+        let mut elements = Vec::with_capacity(list.len());
+        for (i, element) in list.elements().iter().enumerate() {
+            elements.push(if i < skip {
+                element.clone()
+            } else {
+                self.expand_item(element.clone())?
+            });
+        }
+        Ok(rebuilt(elements, &list))
+    }
+
+    /// Expands the unquoted expressions of the quasiquote template, where
+    /// `depth` is the number of quasiquotes surrounding the template.
+    fn expand_quasiquote(&mut self, template: Item, depth: usize) -> VMOutput<Item> {
+        let list = match template {
+            Item::Cons(list) if list.is_null_terminated() => list,
+            template => return Ok(template),
+        };
+        let depth = match list.car() {
+            Item::Name(name) if base(name) == "quasiquote" => depth + 1,
+            Item::Name(name) if base(name) == "unquote" || base(name) == "unquote-splicing" => {
+                if depth == 1 {
+                    return self.expand_elements(list, 1);
+                }
+                depth - 1
+            }
+            _ => depth,
+        };
+        let mut elements = Vec::with_capacity(list.len());
+        for element in list.elements() {
+            elements.push(self.expand_quasiquote(element.clone(), depth)?);
+        }
+        Ok(rebuilt(elements, &list))
+    }
+}
+
+/// Locates the error at the list, unless it is already located.
+fn at(error: VMError, list: &Cons) -> VMError {
+    match list.position() {
+        Some(position) if error.position().is_none() => error.with_position(position),
+        _ => error,
+    }
+}
+
+/// Keeps the position of the original list on the expanded one.
+fn located(item: Item, list: &Cons) -> Item {
+    match (item, list.position()) {
+        (Item::Cons(expanded), Some(position)) => Item::Cons(expanded.with_position(position)),
+        (item, _) => item,
+    }
+}
+
+/// Builds the list of the expanded elements of the list, keeping the
+/// positions of the list and of its elements.
+fn rebuilt(elements: Vec<Item>, list: &Cons) -> Item {
+    match located(gen_list(elements), list) {
+        Item::Cons(expanded) => {
+            Item::Cons(expanded.with_element_positions(list.element_positions().to_vec()))
+        }
+        item => item,
+    }
+}
+
+/// Gives the renamed names of the expanded item, which are not bound
+/// within it, their original names.
+fn resolve_bound(item: &Item) -> Item {
+    let mut bound = HashSet::new();
+    collect_bound(item, &mut bound);
+    resolve(item, &bound)
+}
+
+/// Collects the renamed names, which are bound by a binding form.
+fn collect_bound(item: &Item, bound: &mut HashSet<String>) {
+    let list = match item {
+        Item::Cons(list) => list,
+        _ => return,
+    };
+    let mut bind = |item: &Item| match item {
+        Item::Name(name) if is_renamed(name) => {
+            bound.insert(name.clone());
+        }
+        Item::Cons(names) => {
+            for name in names.iter() {
+                if let Item::Name(name) = name {
+                    if is_renamed(name) {
+                        bound.insert(name.clone());
+                    }
+                }
+            }
+        }
+        _ => {}
+    };
+    match list.car() {
+        Item::Name(name) if base(name) == "quote" => return,
+        Item::Name(name) if base(name) == "lambda" => {
+            if let Some(params) = list.get(1) {
+                bind(params);
+            }
+        }
+        Item::Name(name) if base(name) == "defmacro" => {
+            if let Some(params) = list.get(2) {
+                bind(params);
+            }
+        }
+        Item::Name(name) if ["let", "let*", "letrec"].contains(&base(name)) => {
+            if let Some(Item::Cons(bindings)) = list.get(1) {
+                for binding in bindings.iter() {
+                    if let Item::Cons(binding) = binding {
+                        bind(binding.car());
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    for item in list.iter() {
+        collect_bound(item, bound);
+    }
+}
+
+/// Gives the renamed names, which are not bound, their original names.
+fn resolve(item: &Item, bound: &HashSet<String>) -> Item {
+    match item {
+        Item::Name(name) => Item::Name(resolve_name(name, bound).into()),
+        Item::Cons(list) => match list.car() {
+            Item::Name(name) if base(name) == "quote" => strip(item),
+            Item::Name(name) if base(name) == "quasiquote" => resolve_quasiquote(item, 0, bound),
+            _ => Item::Cons(list.clone().map(|item| resolve(item, bound))),
+        },
+        item => item.clone(),
+    }
+}
+
+fn resolve_name<'a>(name: &'a str, bound: &HashSet<String>) -> &'a str {
+    if bound.contains(name) {
+        return name;
+    }
+    match syntax_rules::original(name) {
+        Some(original) => resolve_name(original, bound),
+        None => name,
+    }
+}
+
+/// Resolves the unquoted expressions of the quasiquote template, and gives
+/// every other name its name in the source code.
+fn resolve_quasiquote(template: &Item, depth: usize, bound: &HashSet<String>) -> Item {
+    let list = match template {
+        Item::Cons(list) => list,
+        template => return strip(template),
+    };
+    let depth = match list.car() {
+        Item::Name(name) if base(name) == "quasiquote" => depth + 1,
+        Item::Name(name) if base(name) == "unquote" || base(name) == "unquote-splicing" => {
+            if depth == 1 {
+                let mut index = 0;
+                return Item::Cons(list.clone().map(|item| {
+                    index += 1;
+                    if index == 1 {
+                        strip(item)
+                    } else {
+                        resolve(item, bound)
+                    }
+                }));
+            }
+            depth - 1
+        }
+        _ => depth,
+    };
+    Item::Cons(
+        list.clone()
+            .map(|item| resolve_quasiquote(item, depth, bound)),
+    )
+}
+
+/// Gives every name of the data its name in the source code.
+fn strip(item: &Item) -> Item {
+    match item {
+        Item::Name(name) => Item::Name(base(name).into()),
+        Item::Cons(list) => Item::Cons(list.clone().map(strip)),
+        item => item.clone(),
+    }
+}
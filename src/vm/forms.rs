@@ -0,0 +1,305 @@
+//! This module rewrites derived special forms into simpler expressions,
+//! which the machine knows how to evaluate.
+
+use super::super::datastructure::{Cons, Item};
+use super::{VMError, VMOutput};
+
+/// Generates a list of the items, where no items is the empty list.
+pub fn gen_list(items: Vec<Item>) -> Item {
+    if items.is_empty() {
+        Item::None
+    } else {
+        Item::Cons(Cons::from(items))
+    }
+}
+
+/// Keeps the position of the original form on the rewritten one, so errors
+/// are reported at the original form.
+fn located(item: Item, form: &Cons) -> Item {
+    match (item, form.position()) {
+        (Item::Cons(list), Some(position)) => Item::Cons(list.with_position(position)),
+        (item, _) => item,
+    }
+}
+
+/// Returns the items of the list, where the empty list has no items.
+fn items(item: &Item) -> Option<Vec<Item>> {
+    match item {
+        Item::None => Some(Vec::new()),
+        Item::Cons(list) if list.is_null_terminated() => Some(list.elements().to_vec()),
+        _ => None,
+    }
+}
+
+/// The bindings of a `let` like form, along with its body.
+type Bindings = (Vec<(String, Item)>, Vec<Item>);
+
+/// Returns the bindings of a `let` like form as pairs of names and
+/// expressions.
+fn bindings(form: &str, list: &Cons) -> VMOutput<Bindings> {
+    let bindings = match list.get(1).and_then(items) {
+        Some(bindings) => bindings,
+        None => return Err(VMError::malformed(form, "expected a list of bindings")),
+    };
+    let bindings = bindings
+        .iter()
+        .map(|binding| match items(binding).as_deref() {
+            Some([Item::Name(name), value]) => Ok((name.clone(), value.clone())),
+            _ => Err(VMError::malformed(
+                form,
+                &format!("expected a binding like (name value), found: {}", binding),
+            )),
+        })
+        .collect::<VMOutput<Vec<_>>>()?;
+    let body = list.elements().get(2..).unwrap_or_default().to_vec();
+    Ok((bindings, body))
+}
+
+fn gen_lambda_call(names: Vec<String>, values: Vec<Item>, body: Vec<Item>) -> Item {
+    let mut lambda = vec![
+        Item::Name("lambda".into()),
+        gen_list(names.into_iter().map(Item::Name).collect()),
+    ];
+    lambda.extend(body);
+    let mut call = vec![gen_list(lambda)];
+    call.extend(values);
+    gen_list(call)
+}
+
+/// Rewrites `(let ((name value) ...) body ...)` into
+/// `((lambda (name ...) body ...) value ...)`.
+pub fn expand_let(list: &Cons) -> VMOutput<Item> {
+    let (bindings, body) = bindings("let", list)?;
+    let (names, values) = bindings.into_iter().unzip();
+    Ok(located(gen_lambda_call(names, values, body), list))
+}
+
+/// Rewrites `(let* (binding ...) body ...)` into nested `let` forms, with
+/// one binding each, so every value can see the bindings before it.
+pub fn expand_let_star(list: &Cons) -> VMOutput<Item> {
+    let (bindings, body) = bindings("let*", list)?;
+    let mut expression = gen_lambda_call(Vec::new(), Vec::new(), body);
+    for (name, value) in bindings.into_iter().rev() {
+        expression = gen_lambda_call(vec![name], vec![value], vec![expression]);
+    }
+    Ok(located(expression, list))
+}
+
+/// Rewrites `(letrec ((name value) ...) body ...)` into a `let`, which
+/// binds every name to `()`, and then sets them to their values, so the
+/// values can refer to each other. The functions bound this way keep their
+/// own scope alive, which leaks the scope, see `Scope`.
+pub fn expand_letrec(list: &Cons) -> VMOutput<Item> {
+    let (bindings, body) = bindings("letrec", list)?;
+    let mut statements: Vec<Item> = bindings
+        .iter()
+        .map(|(name, value)| {
+            gen_list(vec![
+                Item::Name("set!".into()),
+                Item::Name(name.clone()),
+                value.clone(),
+            ])
+        })
+        .collect();
+    statements.extend(body);
+    let (names, values) = bindings
+        .into_iter()
+        .map(|(name, _)| (name, Item::None))
+        .unzip();
+    Ok(located(gen_lambda_call(names, values, statements), list))
+}
+
+/// Returns the arguments of the form, which must have at least `min`, and
+/// at most `max` of them.
+pub fn arguments(form: &str, list: &Cons, min: usize, max: Option<usize>) -> VMOutput<Vec<Item>> {
+    let elements = match items(&Item::Cons(list.clone())) {
+        Some(elements) => elements,
+        None => return Err(VMError::malformed(form, "expected a proper list")),
+    };
+    let found = elements.len() - 1;
+    let expected = match max {
+        Some(max) if max == min => format!("{}", min),
+        Some(max) => format!("{} to {}", min, max),
+        None => format!("at least {}", min),
+    };
+    if found < min || max.is_some_and(|max| found > max) {
+        return Err(VMError::malformed(
+            form,
+            &format!("expected {} arguments, found {}", expected, found),
+        ));
+    }
+    Ok(elements[1..].to_vec())
+}
+
+/// Returns the expressions of a form, which evaluates a sequence of
+/// expressions, like `begin`, `and` or `or`.
+pub fn sequence(form: &str, list: &Cons) -> VMOutput<Vec<Item>> {
+    arguments(form, list, 0, None)
+}
+
+fn gen_begin(body: Vec<Item>) -> Item {
+    let mut begin = vec![Item::Name("begin".into())];
+    begin.extend(body);
+    gen_list(begin)
+}
+
+fn gen_if(test: Item, then: Item, otherwise: Item) -> Item {
+    gen_list(vec![Item::Name("if".into()), test, then, otherwise])
+}
+
+/// Rewrites `(when test body ...)` into `(if test (begin body ...) ())`.
+pub fn expand_when(list: &Cons) -> VMOutput<Item> {
+    let mut elements = arguments("when", list, 1, None)?;
+    let body = elements.split_off(1);
+    let test = elements.remove(0);
+    Ok(located(gen_if(test, gen_begin(body), Item::None), list))
+}
+
+/// Rewrites `(unless test body ...)` into `(if test () (begin body ...))`.
+pub fn expand_unless(list: &Cons) -> VMOutput<Item> {
+    let mut elements = arguments("unless", list, 1, None)?;
+    let body = elements.split_off(1);
+    let test = elements.remove(0);
+    Ok(located(gen_if(test, Item::None, gen_begin(body)), list))
+}
+
+/// Rewrites the first clause of `(cond (test body ...) ...)` into
+/// `(if test (begin body ...) (cond ...))`, where the remaining clauses are
+/// rewritten once they are reached.
+///
+/// A clause without a body results in the value of its test, and an `else`
+/// clause matches unconditionally, but must be the last one.
+pub fn expand_cond(list: &Cons) -> VMOutput<Item> {
+    let mut clauses = sequence("cond", list)?;
+    if clauses.is_empty() {
+        return Ok(Item::None);
+    }
+    let remaining = clauses.split_off(1);
+    let mut body = match items(&clauses[0]) {
+        Some(clause) if !clause.is_empty() => clause,
+        _ => {
+            return Err(VMError::malformed(
+                "cond",
+                &format!(
+                    "expected a clause like (test body ...), found: {}",
+                    clauses[0]
+                ),
+            ))
+        }
+    };
+    let test = body.remove(0);
+    if test == Item::Name("else".into()) {
+        return if remaining.is_empty() {
+            Ok(located(gen_begin(body), list))
+        } else {
+            Err(VMError::malformed(
+                "cond",
+                "the else clause must be the last clause",
+            ))
+        };
+    }
+
+    let mut otherwise = vec![Item::Name("cond".into())];
+    otherwise.extend(remaining);
+    let otherwise = located(gen_list(otherwise), list);
+    let expression = if body.is_empty() {
+        gen_list(vec![Item::Name("or".into()), test, otherwise])
+    } else {
+        gen_if(test, gen_begin(body), otherwise)
+    };
+    Ok(located(expression, list))
+}
+
+fn gen_quoted(item: Item) -> Item {
+    gen_list(vec![Item::Name("quote".into()), item])
+}
+
+fn gen_cons(car: Item, cdr: Item) -> Item {
+    gen_list(vec![Item::Name("cons".into()), car, cdr])
+}
+
+/// Returns the expression of the list, if it is a form like `(name x)`.
+fn unary<'a>(name: &str, item: &'a Item) -> Option<&'a Item> {
+    match item {
+        Item::Cons(list) if list.car() == &Item::Name(name.into()) => match list.elements() {
+            [_, expression] if list.is_null_terminated() => Some(expression),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns true if the template contains no unquotes, which would be
+/// evaluated.
+fn is_constant(template: &Item, depth: usize) -> bool {
+    if let Some(expression) =
+        unary("unquote", template).or_else(|| unary("unquote-splicing", template))
+    {
+        return depth > 0 && is_constant(expression, depth - 1);
+    }
+    if let Some(expression) = unary("quasiquote", template) {
+        return is_constant(expression, depth + 1);
+    }
+    match template {
+        Item::Cons(list) => list.iter().all(|item| is_constant(item, depth)),
+        _ => true,
+    }
+}
+
+/// Rewrites `(quasiquote template)` into an expression building the
+/// template, where `(unquote x)` is replaced by the value of `x`, and
+/// `(unquote-splicing x)` by the elements of the value of `x`.
+///
+/// The expression calls `cons` and `append`, so the rewrite is only correct
+/// as long as those names are not rebound.
+pub fn expand_quasiquote(list: &Cons) -> VMOutput<Item> {
+    let template = arguments("quasiquote", list, 1, Some(1))?.remove(0);
+    Ok(located(quasiquote(template, 0)?, list))
+}
+
+fn quasiquote(template: Item, depth: usize) -> VMOutput<Item> {
+    if is_constant(&template, depth) {
+        return Ok(gen_quoted(template));
+    }
+    for (name, nested) in &[
+        ("unquote", depth.checked_sub(1)),
+        ("unquote-splicing", depth.checked_sub(1)),
+        ("quasiquote", Some(depth + 1)),
+    ] {
+        if let Some(expression) = unary(name, &template) {
+            return match nested {
+                Some(nested) => {
+                    let expression = quasiquote(expression.clone(), *nested)?;
+                    Ok(gen_cons(
+                        gen_quoted(Item::Name((*name).into())),
+                        gen_cons(expression, Item::None),
+                    ))
+                }
+                None if *name == "unquote" => Ok(expression.clone()),
+                None => Err(VMError::malformed(
+                    "unquote-splicing",
+                    "can only be used within a list",
+                )),
+            };
+        }
+    }
+    let list = match template {
+        Item::Cons(list) => list,
+        template => return Ok(gen_quoted(template)),
+    };
+    let mut result = match list.nth_cdr(list.elements().len()) {
+        Some(Item::None) | None => Item::None,
+        Some(tail) => quasiquote(tail, depth)?,
+    };
+    for element in list.elements().iter().rev() {
+        result = match unary("unquote-splicing", element) {
+            Some(expression) if depth == 0 => gen_list(vec![
+                Item::Name("append".into()),
+                expression.clone(),
+                result,
+            ]),
+            _ => gen_cons(quasiquote(element.clone(), depth)?, result),
+        };
+    }
+    Ok(result)
+}
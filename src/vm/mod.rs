@@ -1,31 +1,72 @@
-use super::datastructure::{Cons, EnvItem, EnvItemFunctionWrapped, Environment, Item, Parameters};
+use super::datastructure::{
+    Cons, EnvItem, EnvItemFunctionWrapped, Environment, Item, Parameters, Scope,
+};
+use super::lexer::Position;
 use super::stdlib;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 mod error;
+mod expander;
+mod forms;
+pub mod native;
+mod syntax_rules;
+#[cfg(test)]
+mod tests;
 
-pub use error::VMError;
+pub use error::{TraceFrame, VMError, VMErrorKind};
+pub use native::{FromItem, IntoItem, NativeFunction};
 
 #[derive(Clone, Debug)]
 enum Instruction {
     CondEval(Item),
     ElseEval(Item),
     Eval(Item),
+    /// Evaluates an item at a known position in the source code, so errors
+    /// of the item itself, e.g. of an unbound name, are located at it.
+    EvalAt(Item, Position),
+    /// Evaluates an item in tail position, meaning that the result of the
+    /// current stack frame is the result of the item.
+    TailEval(Item),
     EvalFunction(Item),
     EvalRegister(usize),
+    /// Replaces the nearest binding of the name with the last value of the
+    /// register.
+    Assign(String),
+    /// Evaluates the last value of the register in tail position, e.g. the
+    /// expansion of a macro.
+    TailEvalRegister,
+    /// Drops the last value of the register.
+    Discard,
+    /// Returns the last value of the register from the current stack frame,
+    /// if its truth value matches, or drops it otherwise.
+    ReturnIf(bool),
     //Pop,
     Return(EnvItem),
-    ReturnRegister(usize),
     Call,
     CrashIfNotFunction,
 }
 
+#[derive(Default)]
 struct StackFrame {
     register: VecDeque<EnvItem>,
     instructions: VecDeque<Instruction>,
     ret: (usize, usize),
     condition_success: bool,
+    /// The position of the list being evaluated by this frame, if it is known.
+    position: Option<Position>,
+    /// Whether the list evaluated by this frame is a function application,
+    /// rather than a special form.
+    applies_function: bool,
+    /// The name of the function applied by this frame, once it is called.
+    function: Option<String>,
+    /// Whether the function called by this frame is a lambda.
+    calls_lambda: bool,
+    /// The innermost lambda call replaced by this frame through tail calls.
+    tail_of: Option<TraceFrame>,
 }
 
 impl StackFrame {
@@ -37,14 +78,71 @@ impl StackFrame {
     }
 }
 
-impl Default for StackFrame {
-    fn default() -> Self {
-        StackFrame {
-            register: VecDeque::new(),
-            instructions: VecDeque::new(),
-            ret: (0, 0),
-            condition_success: false,
-        }
+/// Limits on the resources a `Machine` may use for a single call to
+/// `Machine::eval`. A limit of `None` means that the resource is unbounded.
+///
+/// # Example
+///
+/// ```
+/// use minilisp::vm::{Machine, MachineOptions};
+///
+/// let machine = Machine::with_options(MachineOptions {
+///     max_stack_depth: Some(1000),
+///     max_instructions: Some(1_000_000),
+///     ..Default::default()
+/// });
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MachineOptions {
+    /// The maximum amount of stack frames.
+    pub max_stack_depth: Option<usize>,
+    /// The maximum amount of instructions to run.
+    pub max_instructions: Option<usize>,
+    /// The maximum amount of values to allocate. Every closure created by a
+    /// `lambda` counts as one value, and every value returned by a built-in
+    /// function counts as one value, where a list counts as one value plus
+    /// its elements.
+    pub max_allocations: Option<usize>,
+}
+
+/// A handle for interrupting a running `Machine::eval`, possibly from
+/// another thread. The machine checks the handle between every instruction,
+/// and fails with a `VMErrorKind::Interrupted` error when it is interrupted.
+///
+/// # Example
+///
+/// ```
+/// use minilisp::vm::Machine;
+/// use std::thread;
+///
+/// let machine = Machine::default();
+/// let handle = machine.interrupt_handle();
+/// thread::spawn(move || handle.interrupt());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Interrupts the machine. If the machine is not running, then the next
+    /// call to `Machine::eval` is interrupted.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    /// Withdraws an interruption, which has not been handled yet.
+    pub fn reset(&self) {
+        self.interrupted.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+
+    /// Returns whether the machine is interrupted, and resets the handle.
+    fn take(&self) -> bool {
+        self.interrupted.swap(false, Ordering::SeqCst)
     }
 }
 
@@ -52,15 +150,64 @@ pub struct Machine {
     // For future reference:
     // The environment stack could be moved to the stackframe
     env: Environment,
+    /// The macros defined through `defmacro`, which are always global.
+    macros: HashMap<String, EnvItem>,
+    /// The expander of the macros defined through `define-syntax`.
+    syntax: expander::Expander,
     stack: Vec<StackFrame>,
+    options: MachineOptions,
+    instructions_run: usize,
+    allocations: usize,
+    interrupt: InterruptHandle,
 }
 
 type VMOutput<T = ()> = Result<T, error::VMError>;
 
 impl Machine {
+    /// Generates a new machine with the stdlib, which is limited by the
+    /// given options.
+    pub fn with_options(options: MachineOptions) -> Machine {
+        Machine {
+            env: stdlib::stdlib(),
+            macros: HashMap::new(),
+            syntax: expander::Expander::default(),
+            stack: vec![StackFrame::default()],
+            options,
+            instructions_run: 0,
+            allocations: 0,
+            interrupt: InterruptHandle::default(),
+        }
+    }
+
+    /// Returns a handle, which can be used for interrupting the machine.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.interrupt.clone()
+    }
+
+    /// Expands every macro use within the item, until no macro is left, so
+    /// the expanded item can be evaluated by `eval`. The macros of its
+    /// `define-syntax` and `defmacro` forms are defined as they are expanded,
+    /// so the rest of the item can use them.
+    pub fn expand(&mut self, item: Item) -> VMOutput<Item> {
+        self.expand_all(item)
+    }
+
+    /// Expands the item once, if it is a macro use, without expanding the
+    /// expansion or the elements of the item. Any other item is returned
+    /// unchanged.
+    pub fn expand_once(&mut self, item: Item) -> VMOutput<Item> {
+        self.expand_head(item)
+    }
+
     pub fn eval(&mut self, item: Item) -> VMOutput<EnvItem> {
         self.clear_stack()?;
         self.push_instruction(Instruction::Eval(item))?;
+        self.run()
+    }
+
+    /// Runs the instructions of the stack, until the bottom stack frame has
+    /// a single value left.
+    fn run(&mut self) -> VMOutput<EnvItem> {
         while match self.stack.first() {
             Some(sf) => sf.instructions.front().is_some(),
             //match sf.instructions.front() {
@@ -74,7 +221,12 @@ impl Machine {
             #[cfg(feature = "vm-debug")]
             self.print_machine()?;
 
-            self.run_instruction()?;
+            if let Err(error) = self
+                .count_instruction()
+                .and_then(|_| self.run_instruction())
+            {
+                return Err(self.locate(error));
+            }
         }
 
         let mut reg = self.get_current_register()?;
@@ -88,6 +240,79 @@ impl Machine {
         }
     }
 
+    /// Calls the transformer of a `defmacro` macro with the unevaluated
+    /// arguments, and returns the expansion. The transformer runs on a
+    /// stack of its own, so it can be called while the machine is running,
+    /// e.g. by `macroexpand`. The limits of the machine still count the
+    /// instructions of the transformer.
+    fn call_transformer(&mut self, transformer: EnvItem, arguments: &[Item]) -> VMOutput<Item> {
+        let stack = mem::replace(&mut self.stack, vec![StackFrame::default()]);
+        let layers = self.env.suspend();
+        let result = self.push_transformer_call(transformer, arguments);
+        let result = result.and_then(|_| self.run());
+        self.stack = stack;
+        self.env.resume(layers);
+        Ok(result?.into())
+    }
+
+    fn push_transformer_call(&mut self, transformer: EnvItem, arguments: &[Item]) -> VMOutput {
+        self.push_stack_frame()?;
+        self.push_register(transformer)?;
+        for argument in arguments {
+            self.push_register(EnvItem::Data(argument.clone()))?;
+        }
+        self.push_instruction(Instruction::CrashIfNotFunction)?;
+        self.push_instruction(Instruction::Call)
+    }
+
+    /// Adds the Lisp call stack to the error, and the position of the
+    /// innermost expression with a known position, unless the error is
+    /// already located.
+    fn locate(&self, error: VMError) -> VMError {
+        let error = error.with_trace(self.trace());
+        if error.position().is_some() {
+            return error;
+        }
+        match self.stack.iter().rev().find_map(|sf| sf.position) {
+            Some(position) => error.with_position(position),
+            None => error,
+        }
+    }
+
+    /// Generates the Lisp call stack, starting with the outermost call.
+    ///
+    /// Frames replaced by tail calls are lost, except for the innermost
+    /// lambda call replaced by each remaining frame.
+    fn trace(&self) -> Vec<TraceFrame> {
+        let mut trace = Vec::new();
+        for sf in &self.stack {
+            trace.extend(sf.tail_of.clone());
+            if let Some(name) = &sf.function {
+                trace.push(TraceFrame::new(name.clone(), sf.position));
+            }
+        }
+        trace
+    }
+
+    fn count_instruction(&mut self) -> VMOutput {
+        if self.interrupt.take() {
+            return Err(VMError::interrupted());
+        }
+        self.instructions_run += 1;
+        match self.options.max_instructions {
+            Some(max) if self.instructions_run > max => Err(VMError::instruction_limit(max)),
+            _ => Ok(()),
+        }
+    }
+
+    fn allocate(&mut self, size: usize) -> VMOutput {
+        self.allocations += size;
+        match self.options.max_allocations {
+            Some(max) if self.allocations > max => Err(VMError::allocation_limit(max)),
+            _ => Ok(()),
+        }
+    }
+
     fn run_instruction(&mut self) -> VMOutput {
         match self.stack.last_mut() {
             Some(sf) => match sf.instructions.pop_front() {
@@ -95,14 +320,24 @@ impl Machine {
                     Instruction::CondEval(expression) => self.conditional_eval(expression),
                     Instruction::ElseEval(expression) => self.else_eval(expression),
                     Instruction::Eval(i) => self.eval_item(i),
+                    Instruction::EvalAt(i, position) => {
+                        self.eval_item(i).map_err(|error| match error.position() {
+                            Some(_) => error,
+                            None => error.with_position(position),
+                        })
+                    }
+                    Instruction::TailEval(i) => self.tail_eval(i),
                     Instruction::EvalFunction(i) => self.eval_function(i),
                     Instruction::EvalRegister(r) => self.eval_register(r),
+                    Instruction::Assign(name) => self.assign(name),
+                    Instruction::TailEvalRegister => self.tail_eval_register(),
+                    Instruction::Discard => self.pop_register_back().map(|_| ()),
+                    Instruction::ReturnIf(expected) => self.return_if(expected),
                     //Instruction::Pop => {
                     //    self.pop_stack_frame()?;
                     //    Ok(())
                     //}
                     Instruction::Return(i) => self.return_val(i),
-                    Instruction::ReturnRegister(r) => self.return_register(r),
                     Instruction::Call => self.call_function(),
                     Instruction::CrashIfNotFunction => self.crash_if_not_function(),
                 },
@@ -185,6 +420,11 @@ impl Machine {
     }
 
     fn push_stack_frame(&mut self) -> VMOutput {
+        if let Some(max) = self.options.max_stack_depth {
+            if self.stack.len() >= max {
+                return Err(VMError::stack_overflow(max));
+            }
+        }
         if !self.stack.is_empty() {
             //println!("{}", self.stack.len());
             let sp = self.stack.len() - 1;
@@ -198,6 +438,29 @@ impl Machine {
         Ok(())
     }
 
+    /// Replaces the current stack frame with a new one, which returns to
+    /// the same place as the replaced one. The scope of the new frame is
+    /// linked to the scope of the replaced frame, so the bindings are still
+    /// visible while evaluating the new frame.
+    fn replace_stack_frame(&mut self) -> VMOutput {
+        let sf = match self.stack.pop() {
+            Some(sf) => sf,
+            None => Err(VMError::no_stack_frame())?,
+        };
+        let (sp, rp) = sf.ret;
+        let mut new_sf = StackFrame::new(sp, rp);
+        new_sf.tail_of = if sf.calls_lambda {
+            let position = sf.position;
+            sf.function.map(|name| TraceFrame::new(name, position))
+        } else {
+            sf.tail_of
+        };
+        self.stack.push(new_sf);
+        let scope = self.env.current();
+        self.env.enter(scope);
+        Ok(())
+    }
+
     fn pop_stack_frame(&mut self) -> VMOutput<Option<StackFrame>> {
         //println!("{}", self.stack.len());
         self.env.pop();
@@ -209,16 +472,11 @@ impl Machine {
     }
 
     fn get_current_register(&mut self) -> VMOutput<Vec<EnvItem>> {
-        let mut sf = match self.pop_stack_frame()? {
-            Some(sf) => sf,
-            None => Err(VMError::no_stack_frame())?,
-        };
-        let register = sf.register.into();
-        sf.register = VecDeque::new();
-        self.stack.push(sf);
-        Ok(register)
+        let sf = self.get_current_stack_frame_mut()?;
+        Ok(mem::take(&mut sf.register).into())
     }
 
+    #[allow(dead_code)]
     fn get_register(&mut self, register: usize) -> VMOutput<EnvItem> {
         match self
             .get_current_stack_frame_mut()?
@@ -259,7 +517,7 @@ impl Machine {
 
         if test.into() {
             self.get_current_stack_frame_mut()?.condition_success = true;
-            self.eval_item(expression)
+            self.tail_eval(expression)
         } else {
             Ok(())
         }
@@ -267,12 +525,61 @@ impl Machine {
 
     fn else_eval(&mut self, expression: Item) -> VMOutput {
         if !self.get_current_stack_frame()?.condition_success {
-            self.eval_item(expression)
+            self.tail_eval(expression)
         } else {
             Ok(())
         }
     }
 
+    fn assign(&mut self, name: String) -> VMOutput {
+        let value = match self.get_current_stack_frame()?.register.back() {
+            Some(value) => value.clone(),
+            None => return Err(VMError::error("No value to assign")),
+        };
+        if self.env.set(&name, value) {
+            Ok(())
+        } else {
+            Err(VMError::set_unbound(&name))
+        }
+    }
+
+    fn return_if(&mut self, expected: bool) -> VMOutput {
+        let value = self.pop_register_back()?;
+        let test = match &value {
+            EnvItem::Data(d) => d.clone().into(),
+            _ => true,
+        };
+        if test == expected {
+            self.return_val(value)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Pushes the instructions for evaluating the expressions in order,
+    /// where the last one is in tail position, and `between` runs after each
+    /// of the others. No expressions evaluate to `empty`.
+    fn push_sequence(
+        &mut self,
+        mut expressions: Vec<Item>,
+        between: Instruction,
+        empty: Item,
+    ) -> VMOutput {
+        let last = expressions.pop().unwrap_or(empty);
+        for expression in expressions {
+            self.push_instruction(Instruction::Eval(expression))?;
+            self.push_instruction(between.clone())?;
+        }
+        self.push_instruction(Instruction::TailEval(last))
+    }
+
+    fn tail_eval_register(&mut self) -> VMOutput {
+        match self.pop_register_back()? {
+            EnvItem::Data(item) => self.tail_eval(item),
+            value => Err(VMError::error(format!("'{}' cannot be evaluated", value))),
+        }
+    }
+
     fn eval_register(&mut self, register: usize) -> VMOutput {
         let item = self.get_register_item(register)?;
         self.eval_item(item)
@@ -286,36 +593,111 @@ impl Machine {
         }
     }
 
+    /// Evaluates the item as the result of the current stack frame.
+    ///
+    /// Function calls reuse the current stack frame instead of pushing a new
+    /// one, which means that recursion in tail position runs in constant
+    /// space.
+    fn tail_eval(&mut self, item: Item) -> VMOutput {
+        match item {
+            Item::Cons(list) if self.stack.len() > 1 => {
+                self.replace_stack_frame()?;
+                self.push_call(list)
+            }
+            Item::Cons(list) => self.eval_list(list),
+            item => {
+                self.eval_item(item)?;
+                let value = self.pop_register_back()?;
+                self.return_val(value)
+            }
+        }
+    }
+
     fn eval_special_function(&mut self, name: &str, list: Cons) -> VMOutput<usize> {
         let mut skip = 1;
 
         match name {
             "def" | "define" => {
+                let arguments = forms::arguments(name, &list, 2, Some(2))?;
                 self.push_register(stdlib::def_env())?;
-                self.push_register(EnvItem::Data(list.cadr().clone()))?;
+                self.push_register(EnvItem::Data(arguments[0].clone()))?;
                 skip = 2;
             }
             "quote" => {
-                if list.len() != 2 {
-                    return Err(VMError::error("Too many arguments to function"));
-                }
+                let mut arguments = forms::arguments("quote", &list, 1, Some(1))?;
                 self.push_register(gen_quote())?;
-                self.push_register(EnvItem::Data(list.cadr().clone()))?;
+                self.push_register(EnvItem::Data(arguments.remove(0)))?;
                 skip = 2;
             }
             "if" => {
-                if list.len() != 4 {
-                    return Err(VMError::error(
-                        "Incorrect amount of arguments for the 'if' function",
-                    ));
-                }
+                let arguments = forms::arguments("if", &list, 2, Some(3))?;
+                // Without an else branch, a false test results in the empty list
+                let otherwise = arguments.get(2).cloned().unwrap_or(Item::None);
                 self.push_register(gen_quote())?;
-                self.push_instruction(Instruction::Eval(list.cadr().clone()))?;
-                self.push_instruction(Instruction::CondEval(list.caddr().clone()))?;
-                self.push_instruction(Instruction::ElseEval(list.cadddr().clone()))?;
-                skip = 4;
+                self.push_instruction(Instruction::Eval(arguments[0].clone()))?;
+                self.push_instruction(Instruction::CondEval(arguments[1].clone()))?;
+                self.push_instruction(Instruction::ElseEval(otherwise))?;
+                skip = list.len();
+            }
+            "set!" => {
+                let (name, value) = match forms::arguments("set!", &list, 2, Some(2))?.as_slice() {
+                    [Item::Name(name), value] => (name.clone(), value.clone()),
+                    _ => return Err(VMError::malformed("set!", "expected a name to set")),
+                };
+                self.push_register(gen_quote())?;
+                self.push_instruction(Instruction::Eval(value))?;
+                self.push_instruction(Instruction::Assign(name))?;
+                skip = 3;
+            }
+            "begin" => {
+                let expressions = forms::sequence("begin", &list)?;
+                self.push_sequence(expressions, Instruction::Discard, Item::None)?;
+                skip = list.len();
+            }
+            "and" => {
+                let expressions = forms::sequence("and", &list)?;
+                self.push_sequence(
+                    expressions,
+                    Instruction::ReturnIf(false),
+                    Item::Boolean(true),
+                )?;
+                skip = list.len();
+            }
+            "or" => {
+                let expressions = forms::sequence("or", &list)?;
+                self.push_sequence(
+                    expressions,
+                    Instruction::ReturnIf(true),
+                    Item::Boolean(false),
+                )?;
+                skip = list.len();
+            }
+            "cond" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_cond(&list)?))?;
+                skip = list.len();
+            }
+            "when" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_when(&list)?))?;
+                skip = list.len();
+            }
+            "unless" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_unless(&list)?))?;
+                skip = list.len();
+            }
+            "let" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_let(&list)?))?;
+                skip = list.len();
+            }
+            "let*" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_let_star(&list)?))?;
+                skip = list.len();
+            }
+            "letrec" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_letrec(&list)?))?;
+                skip = list.len();
             }
             "eval" => {
+                forms::arguments("eval", &list, 1, Some(1))?;
                 self.push_register(gen_quote())?;
                 self.push_instruction(Instruction::EvalRegister(1))?;
                 self.push_instruction(Instruction::Call)?;
@@ -324,24 +706,100 @@ impl Machine {
                 self.push_register(gen_quote())?;
             }
             "lambda" => {
-                let var_bindings = generate_parameter_list(list.cadr())?;
+                let mut arguments = forms::arguments("lambda", &list, 1, None)?;
+                let body = arguments.split_off(1);
+                let var_bindings = generate_parameter_list(&arguments[0])?;
+                self.allocate(1)?;
                 self.push_instruction(Instruction::Return(gen_lambda(
-                    list.cddr_list(),
+                    body,
                     var_bindings,
+                    self.env.current(),
                 )))?;
                 skip = list.len();
             }
-            _ => {
-                self.push_instruction(Instruction::EvalFunction(Item::Name(name.into())))?;
+            "defmacro" => {
+                let name = self.define_macro(&list, self.env.current())?;
+                self.push_instruction(Instruction::Return(EnvItem::Data(Item::Name(name))))?;
+                skip = list.len();
+            }
+            "quasiquote" => {
+                self.push_instruction(Instruction::TailEval(forms::expand_quasiquote(&list)?))?;
+                skip = list.len();
+            }
+            "define-syntax" => {
+                let expansion = self.expand(Item::Cons(list.clone()))?;
+                self.push_instruction(Instruction::TailEval(expansion))?;
+                skip = list.len();
             }
+            _ if self.syntax.is_macro(name) => {
+                let expansion = self.expand(Item::Cons(list.clone()))?;
+                self.push_instruction(Instruction::TailEval(expansion))?;
+                skip = list.len();
+            }
+            "unquote" | "unquote-splicing" => {
+                return Err(VMError::malformed(
+                    name,
+                    "can only be used within a quasiquote",
+                ));
+            }
+            _ => match self.macros.get(name) {
+                Some(transformer) => {
+                    let transformer = transformer.clone();
+                    self.push_macro_call(transformer, &list)?;
+                    skip = list.len();
+                }
+                None => {
+                    self.push_instruction(Instruction::EvalFunction(Item::Name(name.into())))?;
+                }
+            },
         };
 
         Ok(skip)
     }
 
+    /// Defines the macro of the `defmacro` form, whose transformer is a
+    /// lambda created within the scope. Returns the name of the macro.
+    fn define_macro(&mut self, list: &Cons, scope: Scope) -> VMOutput<String> {
+        let mut arguments = forms::arguments("defmacro", list, 2, None)?;
+        let body = arguments.split_off(2);
+        let name = match &arguments[0] {
+            Item::Name(name) => name.clone(),
+            _ => return Err(VMError::malformed("defmacro", "expected a name")),
+        };
+        let params = generate_parameter_list(&arguments[1])?;
+        let transformer = match gen_lambda(body, params, scope) {
+            EnvItem::Function(_, func, params, scope) => {
+                EnvItem::Function(name.clone(), func, params, scope)
+            }
+            transformer => transformer,
+        };
+        self.macros.insert(name.clone(), transformer);
+        Ok(name)
+    }
+
+    /// Pushes the instructions for expanding the macro use, and evaluating
+    /// the expansion in its place. The transformer is called in a new stack
+    /// frame, with the unevaluated arguments of the list.
+    fn push_macro_call(&mut self, transformer: EnvItem, list: &Cons) -> VMOutput {
+        self.push_instruction(Instruction::TailEvalRegister)?;
+        self.push_stack_frame()?;
+        self.push_register(transformer)?;
+        for argument in list.elements().iter().skip(1) {
+            self.push_register(EnvItem::Data(argument.clone()))?;
+        }
+        Ok(())
+    }
+
     fn eval_list(&mut self, list: Cons) -> VMOutput {
         self.push_stack_frame()?;
+        self.push_call(list)
+    }
 
+    /// Pushes the instructions for evaluating the list as a function call
+    /// onto the current stack frame.
+    fn push_call(&mut self, list: Cons) -> VMOutput {
+        let sf = self.get_current_stack_frame_mut()?;
+        sf.position = list.position();
         let skip;
 
         if let Item::Name(name) = list.car() {
@@ -352,8 +810,11 @@ impl Machine {
         }
 
         self.push_instruction(Instruction::CrashIfNotFunction)?;
-        for item in list.iter().skip(skip) {
-            self.push_instruction(Instruction::Eval(item.clone()))?
+        for (i, item) in list.iter().enumerate().skip(skip) {
+            self.push_instruction(match list.position_of(i) {
+                Some(position) => Instruction::EvalAt(item.clone(), position),
+                None => Instruction::Eval(item.clone()),
+            })?
         }
         self.push_instruction(Instruction::Call)?;
         Ok(())
@@ -362,15 +823,17 @@ impl Machine {
     fn eval_name(&mut self, name: String) -> VMOutput {
         match self.env.lookup(&name) {
             EnvItem::Data(item) => self.push_register(EnvItem::Data(item)),
-            EnvItem::Function(name, func, params) => {
-                self.push_register(EnvItem::Function(name, func, params))
+            EnvItem::Function(name, func, params, scope) => {
+                self.push_register(EnvItem::Function(name, func, params, scope))
             }
-            EnvItem::VariableBinding(var) => self.push_register(EnvItem::VariableBinding(var)),
-            EnvItem::None => Err(VMError::error(format!("'{}' not found", name))),
+            // The rest parameter of a lambda is seen as a list by its body
+            var @ EnvItem::VariableBinding(_) => self.push_register(EnvItem::Data(Item::from(var))),
+            EnvItem::None => Err(VMError::unbound_name(&name)),
         }
     }
 
     fn eval_function(&mut self, item: Item) -> VMOutput {
+        self.get_current_stack_frame_mut()?.applies_function = true;
         match item {
             Item::Cons(list) => self.eval_list(list),
             Item::Name(n) => self.eval_function_name(n),
@@ -380,21 +843,20 @@ impl Machine {
 
     fn eval_function_name(&mut self, name: String) -> VMOutput {
         match self.env.lookup(&name) {
-            EnvItem::Function(name, func, params) => {
-                self.push_register(EnvItem::Function(name, func, params))
+            EnvItem::Function(name, func, params, scope) => {
+                self.push_register(EnvItem::Function(name, func, params, scope))
             }
-            EnvItem::Data(_) | EnvItem::VariableBinding(_) => Err(VMError::error(format!(
-                "'{}' is not bound to a function",
-                name
-            ))),
-            EnvItem::None => Err(VMError::error(format!("'{}' not found", name))),
+            EnvItem::Data(_) | EnvItem::VariableBinding(_) => {
+                Err(VMError::not_bound_to_function(&name))
+            }
+            EnvItem::None => Err(VMError::unbound_name(&name)),
         }
     }
 
     fn crash_if_not_function(&self) -> VMOutput {
         match self.stack.last() {
             Some(sf) => match sf.register.front() {
-                Some(EnvItem::Function(_, _, _)) => Ok(()),
+                Some(EnvItem::Function(_, _, _, _)) => Ok(()),
                 _ => Err(VMError::not_a_function()),
             },
             None => Err(VMError::no_stack_frame()),
@@ -402,17 +864,31 @@ impl Machine {
     }
 
     fn call_function(&mut self) -> VMOutput {
-        let (_, func, params) = match self.pop_register()? {
-            EnvItem::Function(n, f, p) => (n, f, p),
+        let (name, func, params, scope) = match self.pop_register()? {
+            EnvItem::Function(n, f, p, s) => (n, f, p, s),
             _ => Err(VMError::not_a_function())?,
         };
+        let sf = self.get_current_stack_frame_mut()?;
+        if sf.applies_function {
+            sf.function = Some(name);
+        }
+        let is_native = scope.is_none();
+        sf.calls_lambda = !is_native;
         let args = self.get_current_register()?;
+        if let Some(scope) = scope {
+            self.env.enter(scope);
+        }
         self.bind_variables(params, args)?;
 
         match func(self) {
             Ok(output) => {
+                if is_native {
+                    // The body of a lambda only returns values, which were
+                    // allocated by the functions it calls
+                    self.allocate(allocation_size(&output))?;
+                }
                 match self.get_last_instruction() {
-                    Ok(Instruction::Return(_)) | Ok(Instruction::ReturnRegister(_)) => Ok(()),
+                    Ok(Instruction::Return(_)) | Ok(Instruction::TailEval(_)) => Ok(()),
                     _ => self.push_instruction(Instruction::Return(output)),
                 }
                 //self.push_instruction(Instruction::Pop)
@@ -429,11 +905,6 @@ impl Machine {
         Ok(())
     }
 
-    fn return_register(&mut self, register: usize) -> VMOutput {
-        let item = self.get_register(register)?;
-        self.return_val(item)
-    }
-
     fn bind_variables(&mut self, params: Parameters, args: Vec<EnvItem>) -> VMOutput {
         match params {
             Parameters::All(all) => {
@@ -458,12 +929,15 @@ impl Machine {
         params: Parameters,
     ) -> VMOutput {
         self.push_stack_frame()?;
-        self.push_register(EnvItem::Function(name, func, params))?;
+        self.push_register(EnvItem::Function(name, func, params, None))?;
         Ok(())
     }
 
     fn clear_stack(&mut self) -> VMOutput {
         self.stack = vec![StackFrame::default()];
+        self.env.clear();
+        self.instructions_run = 0;
+        self.allocations = 0;
         Ok(())
     }
 
@@ -475,6 +949,25 @@ impl Machine {
         Ok(())
     }
 
+    /// Defines a global function, which calls the Rust function. See the
+    /// `native` module for the supported signatures.
+    pub fn register_fn<T, Args, F>(&mut self, name: T, function: F) -> VMOutput
+    where
+        T: Into<String>,
+        F: NativeFunction<Args> + 'static,
+    {
+        let name = name.into();
+        self.define(name.clone(), native::native_fn(name, function))
+    }
+
+    /// Looks up a global value, i.e. one bound through `define`.
+    pub fn lookup_global(&self, key: &str) -> Option<EnvItem> {
+        match self.env.lookup_global(key) {
+            EnvItem::None => None,
+            item => Some(item),
+        }
+    }
+
     pub fn lookup<T>(&mut self, key: T) -> VMOutput<EnvItem>
     where
         T: Into<String>,
@@ -503,11 +996,25 @@ impl Machine {
 
 impl Default for Machine {
     fn default() -> Machine {
-        Machine {
-            env: stdlib::stdlib(),
-            stack: vec![StackFrame::default()],
+        Machine::with_options(MachineOptions::default())
+    }
+}
+
+/// Calculates the amount of values an item, returned by a built-in function,
+/// counts as.
+fn allocation_size(item: &EnvItem) -> usize {
+    fn size(item: &Item) -> usize {
+        match item {
+            Item::Cons(list) => 1 + list.iter().map(size).sum::<usize>(),
+            _ => 1,
         }
     }
+
+    match item {
+        EnvItem::Data(item) => size(item),
+        EnvItem::VariableBinding(items) => 1 + items.iter().map(allocation_size).sum::<usize>(),
+        _ => 1,
+    }
 }
 
 #[allow(dead_code)]
@@ -520,43 +1027,56 @@ fn gen_quote() -> EnvItem {
         "quote".into(),
         Rc::new(stdlib::quote),
         Parameters::Individual(vec!["value".into()]),
+        None,
     )
 }
 
 fn generate_parameter_list(item: &Item) -> VMOutput<Parameters> {
     match item {
+        Item::None => Ok(Parameters::Individual(Vec::new())),
         Item::Cons(list) => Ok(Parameters::Individual(
             list.iter()
                 .map(|i| {
                     if let Item::Name(name) = i {
                         Ok(name.clone())
                     } else {
-                        Err(VMError::error(format!("Parameter '{}' is not a name", i)))
+                        Err(VMError::malformed(
+                            "lambda",
+                            &format!("parameter '{}' is not a name", i),
+                        ))
                     }
                 })
                 .collect::<Result<Vec<String>, VMError>>()?,
         )),
         Item::Name(name) => Ok(Parameters::All(name.clone())),
-        _ => Err(VMError::error(format!(
-            "Parameter '{}' is not a name",
-            item
-        ))),
+        _ => Err(VMError::malformed(
+            "lambda",
+            &format!("parameter '{}' is not a name", item),
+        )),
     }
 }
 
-pub fn gen_lambda(body: Vec<Item>, params: Parameters) -> EnvItem {
+/// Generates the function for a lambda expression.
+///
+/// The `scope` is the scope the lambda is created within, which the body
+/// will be evaluated within when the function is called.
+pub fn gen_lambda(body: Vec<Item>, params: Parameters, scope: Scope) -> EnvItem {
     EnvItem::Function(
         "lambda".into(),
         Rc::new(move |machine: &mut Machine| {
-            let body_len = body.len();
-            for expr in body.iter() {
-                machine.push_instruction(Instruction::Eval(expr.clone()))?;
+            if let Some((last, init)) = body.split_last() {
+                for expr in init.iter() {
+                    machine.push_instruction(Instruction::Eval(expr.clone()))?;
+                }
+                machine.push_instruction(Instruction::TailEval(last.clone()))?;
+            } else {
+                machine.push_instruction(Instruction::Return(EnvItem::Data(Item::None)))?;
             }
-            machine.push_instruction(Instruction::ReturnRegister(body_len - 1))?;
 
             Ok(EnvItem::None)
         }),
         params,
+        Some(scope),
     )
 }
 //, machine: &mut Machine) -> FunctionOutput {
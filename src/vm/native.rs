@@ -0,0 +1,378 @@
+//! This module converts Rust functions into functions of the language.
+//!
+//! A Rust function, or closure, can be registered on the machine, as long as
+//! its arguments implement `FromItem`, and it returns a `Result` with a value
+//! implementing `IntoItem`. The arity of the function is derived from its
+//! signature, and the arguments are converted before the function is called.
+//!
+//! # Example
+//!
+//! ```
+//! use minilisp::datastructure::{Item, Number};
+//! use minilisp::stdlib::error::EvalError;
+//! use minilisp::vm::Machine;
+//!
+//! let mut machine = Machine::default();
+//! machine
+//!     .register_fn("repeat", |s: String, n: i64| -> Result<String, EvalError> {
+//!         Ok(s.repeat(n as usize))
+//!     })
+//!     .unwrap();
+//! let result = minilisp::run("(repeat \"ab\" 3)", &mut machine).unwrap();
+//! assert_eq!(result, Item::String("ababab".into()));
+//! ```
+
+use super::super::datastructure::{
+    Cons, EnvItem, Foreign, FunctionOutput, Item, Number, Parameters,
+};
+use super::super::stdlib::error::{self, EvalError, EvalErrorCode};
+use super::Machine;
+use std::any::{self, Any};
+use std::rc::Rc;
+
+/// Conversion of the arguments of native functions.
+pub trait FromItem: Sized {
+    /// Converts the value, or fails with an `E0012` error, if the value has
+    /// an incompatible type.
+    fn from_item(item: EnvItem) -> Result<Self, EvalError>;
+}
+
+/// Conversion of the results of native functions.
+pub trait IntoItem {
+    fn into_item(self) -> EnvItem;
+}
+
+fn incompatible(expected: &str, found: &EnvItem) -> EvalError {
+    EvalError::new(
+        EvalErrorCode::E0012,
+        format!("Expected {}, found: {}", expected, found),
+    )
+}
+
+impl FromItem for EnvItem {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        Ok(item)
+    }
+}
+
+impl FromItem for Item {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(item) => Ok(item),
+            item => Err(incompatible("data", &item)),
+        }
+    }
+}
+
+impl FromItem for Number {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Number(n)) => Ok(n),
+            item => Err(incompatible("a number", &item)),
+        }
+    }
+}
+
+impl FromItem for i64 {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Number(n)) => match n.to_i64() {
+                Some(i) => Ok(i),
+                None => Err(incompatible(
+                    "an integer within the range of an i64",
+                    &EnvItem::Data(Item::Number(n)),
+                )),
+            },
+            item => Err(incompatible("an integer", &item)),
+        }
+    }
+}
+
+impl FromItem for f64 {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Number(n)) => Ok(n.to_f64()),
+            item => Err(incompatible("a number", &item)),
+        }
+    }
+}
+
+impl FromItem for bool {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Boolean(b)) => Ok(b),
+            item => Err(incompatible("a boolean", &item)),
+        }
+    }
+}
+
+impl FromItem for String {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::String(s)) => Ok(s),
+            item => Err(incompatible("a string", &item)),
+        }
+    }
+}
+
+impl FromItem for char {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Char(c)) => Ok(c),
+            item => Err(incompatible("a character", &item)),
+        }
+    }
+}
+
+impl FromItem for Cons {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Cons(list)) => Ok(list),
+            item => Err(incompatible("a list", &item)),
+        }
+    }
+}
+
+impl FromItem for Foreign {
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Foreign(foreign)) => Ok(foreign),
+            item => Err(incompatible("a foreign value", &item)),
+        }
+    }
+}
+
+/// Foreign values are downcast to the Rust type `T`.
+impl<T> FromItem for Rc<T>
+where
+    T: Any,
+{
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::Foreign(ref foreign)) => match foreign.downcast() {
+                Some(value) => Ok(value),
+                None => Err(incompatible(any::type_name::<T>(), &item)),
+            },
+            item => Err(incompatible(any::type_name::<T>(), &item)),
+        }
+    }
+}
+
+/// The empty list is converted to `None`.
+impl<T> FromItem for Option<T>
+where
+    T: FromItem,
+{
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::None) => Ok(None),
+            item => T::from_item(item).map(Some),
+        }
+    }
+}
+
+impl<T> FromItem for Vec<T>
+where
+    T: FromItem,
+{
+    fn from_item(item: EnvItem) -> Result<Self, EvalError> {
+        match item {
+            EnvItem::Data(Item::None) => Ok(Vec::new()),
+            EnvItem::Data(Item::Cons(list)) => Vec::from(list)
+                .into_iter()
+                .map(|item| T::from_item(EnvItem::Data(item)))
+                .collect(),
+            item => Err(incompatible("a list", &item)),
+        }
+    }
+}
+
+impl IntoItem for EnvItem {
+    fn into_item(self) -> EnvItem {
+        self
+    }
+}
+
+impl IntoItem for Item {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(self)
+    }
+}
+
+impl IntoItem for Number {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Number(self))
+    }
+}
+
+impl IntoItem for i64 {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Number(Number::Integer(self)))
+    }
+}
+
+impl IntoItem for f64 {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Number(Number::Real(self)))
+    }
+}
+
+impl IntoItem for bool {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Boolean(self))
+    }
+}
+
+impl IntoItem for String {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::String(self))
+    }
+}
+
+impl IntoItem for &str {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::String(self.into()))
+    }
+}
+
+impl IntoItem for char {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Char(self))
+    }
+}
+
+impl IntoItem for Cons {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Cons(self))
+    }
+}
+
+impl IntoItem for Foreign {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Foreign(self))
+    }
+}
+
+/// The value is shared with the script as a foreign value.
+impl<T> IntoItem for Rc<T>
+where
+    T: Any,
+{
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::Foreign(Foreign::from_rc(self)))
+    }
+}
+
+/// The unit is converted to the empty list.
+impl IntoItem for () {
+    fn into_item(self) -> EnvItem {
+        EnvItem::Data(Item::None)
+    }
+}
+
+/// `None` is converted to the empty list.
+impl<T> IntoItem for Option<T>
+where
+    T: IntoItem,
+{
+    fn into_item(self) -> EnvItem {
+        match self {
+            Some(value) => value.into_item(),
+            None => EnvItem::Data(Item::None),
+        }
+    }
+}
+
+impl<T> IntoItem for Vec<T>
+where
+    T: IntoItem,
+{
+    fn into_item(self) -> EnvItem {
+        if self.is_empty() {
+            return EnvItem::Data(Item::None);
+        }
+        let list: Vec<Item> = self
+            .into_iter()
+            .map(|value| Item::from(value.into_item()))
+            .collect();
+        EnvItem::Data(Item::Cons(Cons::from(list)))
+    }
+}
+
+/// A Rust function, which can be called with the arguments `Args`.
+///
+/// This is implemented for every function and closure taking up to six
+/// arguments implementing `FromItem`, and returning a
+/// `Result<impl IntoItem, EvalError>`.
+pub trait NativeFunction<Args> {
+    /// The amount of arguments of the function.
+    const ARITY: usize;
+
+    /// Converts the arguments, and calls the function with them. The name
+    /// is used for describing errors.
+    fn call(&self, name: &str, args: Vec<EnvItem>) -> FunctionOutput;
+}
+
+macro_rules! impl_native_function {
+    ($arity:expr; $($arg:ident),*) => {
+        impl<F, R, $($arg,)*> NativeFunction<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<R, EvalError>,
+            R: IntoItem,
+            $($arg: FromItem,)*
+        {
+            const ARITY: usize = $arity;
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, name: &str, args: Vec<EnvItem>) -> FunctionOutput {
+                if args.len() != $arity {
+                    return Err(error::mismatch_arguments(name, $arity, args.len()));
+                }
+                let found = args.len();
+                let mut args = args.into_iter().enumerate();
+                $(
+                    let $arg = match args.next() {
+                        Some((i, arg)) => $arg::from_item(arg).map_err(|e| EvalError {
+                            message: format!("Argument {} of '{}': {}", i + 1, name, e.message),
+                            ..e
+                        })?,
+                        None => return Err(error::mismatch_arguments(name, $arity, found)),
+                    };
+                )*
+                Ok(self($($arg),*)?.into_item())
+            }
+        }
+    };
+}
+
+impl_native_function!(0;);
+impl_native_function!(1; A);
+impl_native_function!(2; A, B);
+impl_native_function!(3; A, B, C);
+impl_native_function!(4; A, B, C, D);
+impl_native_function!(5; A, B, C, D, E);
+impl_native_function!(6; A, B, C, D, E, G);
+
+/// Generates a function of the language, which calls the Rust function.
+pub fn native_fn<T, Args, F>(name: T, function: F) -> EnvItem
+where
+    T: Into<String>,
+    F: NativeFunction<Args> + 'static,
+{
+    let name = name.into();
+    let params: Vec<String> = (0..F::ARITY).map(|i| format!("arg{}", i)).collect();
+    let names = params.clone();
+    let function_name = name.clone();
+    EnvItem::Function(
+        name,
+        Rc::new(move |machine: &mut Machine| {
+            let mut args = Vec::with_capacity(names.len());
+            for param in &names {
+                args.push(machine.lookup(param.as_str())?);
+            }
+            function.call(&function_name, args)
+        }),
+        Parameters::Individual(params),
+        None,
+    )
+}
@@ -0,0 +1,381 @@
+//! This module implements the macros of `syntax-rules`, which rewrite a
+//! form by matching it against a list of patterns, and filling in the
+//! template of the first pattern that matches.
+
+use super::super::datastructure::{Cons, Item};
+use super::forms::gen_list;
+use super::{VMError, VMOutput};
+use std::collections::HashMap;
+
+/// Separates a name from the number of its renaming. Names introduced by a
+/// template are renamed to e.g. `tmp,3`, which cannot be written as a plain
+/// name, since the lexer reads `,` as an unquote. Such names are printed
+/// between bars, like `|tmp,3|`, so expanded code reads back the same.
+const RENAME_SEPARATOR: char = ',';
+
+/// Returns the name, which the name was renamed from, if it is a renamed
+/// name.
+pub fn original(name: &str) -> Option<&str> {
+    name.rfind(RENAME_SEPARATOR).map(|i| &name[..i])
+}
+
+/// Returns the name as it was written in the source code, before any
+/// renaming.
+pub fn base(name: &str) -> &str {
+    match name.find(RENAME_SEPARATOR) {
+        Some(i) => &name[..i],
+        None => name,
+    }
+}
+
+/// Returns true if the name is introduced by a template.
+pub fn is_renamed(name: &str) -> bool {
+    name.contains(RENAME_SEPARATOR)
+}
+
+/// Renames the names introduced by a single expansion, where every
+/// occurrence of a name gets the same new name.
+pub struct Renamer<'a> {
+    counter: &'a mut usize,
+    names: HashMap<String, String>,
+}
+
+impl<'a> Renamer<'a> {
+    pub fn new(counter: &'a mut usize) -> Renamer<'a> {
+        Renamer {
+            counter,
+            names: HashMap::new(),
+        }
+    }
+
+    fn rename(&mut self, name: &str) -> String {
+        if let Some(renamed) = self.names.get(name) {
+            return renamed.clone();
+        }
+        *self.counter += 1;
+        let renamed = format!("{}{}{}", name, RENAME_SEPARATOR, self.counter);
+        self.names.insert(name.into(), renamed.clone());
+        renamed
+    }
+}
+
+/// The value of a pattern variable, where a variable followed by an
+/// ellipsis matches a sequence of values.
+#[derive(Clone, Debug)]
+enum Binding {
+    Single(Item),
+    Sequence(Vec<Binding>),
+}
+
+type Bindings = HashMap<String, Binding>;
+
+/// A macro defined by `(syntax-rules (literal ...) (pattern template) ...)`.
+#[derive(Clone, Debug)]
+pub struct SyntaxRules {
+    name: String,
+    ellipsis: String,
+    literals: Vec<String>,
+    rules: Vec<(Vec<Item>, Item)>,
+}
+
+impl SyntaxRules {
+    /// Reads the `syntax-rules` form of the macro with the name. A custom
+    /// ellipsis can be given as in `(syntax-rules ::: (literal ...) ...)`.
+    pub fn new(name: &str, spec: &Item) -> VMOutput<SyntaxRules> {
+        let malformed = |message: &str| VMError::malformed("syntax-rules", message);
+        let elements = match spec {
+            Item::Cons(list)
+                if list.is_null_terminated()
+                    && list.get(0).and_then(base_name) == Some("syntax-rules") =>
+            {
+                &list.elements()[1..]
+            }
+            _ => return Err(malformed("expected (syntax-rules (literal ...) rule ...)")),
+        };
+        let (ellipsis, elements) = match elements.split_first() {
+            Some((Item::Name(ellipsis), rest)) => (ellipsis.clone(), rest),
+            _ => ("...".into(), elements),
+        };
+        let (literals, rules) = match elements.split_first() {
+            Some((literals, rules)) => (list_of(literals), rules),
+            None => (None, elements),
+        };
+        let literals = match literals {
+            Some(literals) => literals
+                .iter()
+                .map(|literal| match literal {
+                    Item::Name(name) => Ok(name.clone()),
+                    _ => Err(malformed(&format!("literal '{}' is not a name", literal))),
+                })
+                .collect::<VMOutput<Vec<_>>>()?,
+            None => return Err(malformed("expected a list of literals")),
+        };
+
+        let mut syntax = SyntaxRules {
+            name: name.into(),
+            ellipsis,
+            literals,
+            rules: Vec::new(),
+        };
+        for rule in rules {
+            let (pattern, template) = match list_of(rule).as_deref() {
+                Some([Item::Cons(pattern), template]) if pattern.is_null_terminated() => {
+                    (pattern.elements()[1..].to_vec(), template.clone())
+                }
+                _ => {
+                    return Err(malformed(&format!(
+                        "expected a rule like ((_ pattern ...) template), found: {}",
+                        rule
+                    )))
+                }
+            };
+            syntax.check_pattern(&pattern)?;
+            syntax.rules.push((pattern, template));
+        }
+        Ok(syntax)
+    }
+
+    /// Rewrites the use of the macro with the template of the first rule,
+    /// that matches it.
+    pub fn expand(&self, form: &Cons, renamer: &mut Renamer) -> VMOutput<Item> {
+        let arguments = match list_of(&Item::Cons(form.clone())) {
+            Some(elements) => elements[1..].to_vec(),
+            None => return Err(VMError::malformed(&self.name, "expected a proper list")),
+        };
+        for (pattern, template) in &self.rules {
+            let mut bindings = Bindings::new();
+            if self.matches_list(pattern, &arguments, &mut bindings) {
+                return self.instantiate(template, &bindings, renamer);
+            }
+        }
+        Err(VMError::malformed(
+            &self.name,
+            &format!("no rule matches {}", form),
+        ))
+    }
+
+    fn is_ellipsis(&self, item: &Item) -> bool {
+        base_name(item) == Some(base(&self.ellipsis))
+    }
+
+    fn is_literal(&self, name: &str) -> bool {
+        self.literals
+            .iter()
+            .any(|literal| base(literal) == base(name))
+    }
+
+    /// Checks that every list of the pattern has at most one ellipsis, which
+    /// follows a pattern.
+    fn check_pattern(&self, patterns: &[Item]) -> VMOutput {
+        let ellipses: Vec<usize> = (0..patterns.len())
+            .filter(|&i| self.is_ellipsis(&patterns[i]))
+            .collect();
+        if ellipses.len() > 1 || ellipses.first() == Some(&0) {
+            return Err(VMError::malformed(
+                &self.name,
+                "an ellipsis must follow a pattern, and appear once per list",
+            ));
+        }
+        for pattern in patterns {
+            if let Some(elements) = list_of(pattern) {
+                self.check_pattern(&elements)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, pattern: &Item, form: &Item, bindings: &mut Bindings) -> bool {
+        match pattern {
+            Item::Name(name) if base(name) == "_" => true,
+            Item::Name(name) if self.is_literal(name) => base_name(form) == Some(base(name)),
+            Item::Name(name) => {
+                bindings.insert(name.clone(), Binding::Single(form.clone()));
+                true
+            }
+            Item::Cons(_) => match (list_of(pattern), list_of(form)) {
+                (Some(patterns), Some(forms)) => self.matches_list(&patterns, &forms, bindings),
+                _ => false,
+            },
+            pattern => pattern == form,
+        }
+    }
+
+    fn matches_list(&self, patterns: &[Item], forms: &[Item], bindings: &mut Bindings) -> bool {
+        let ellipsis = match patterns.iter().position(|p| self.is_ellipsis(p)) {
+            Some(ellipsis) => ellipsis,
+            None => {
+                return patterns.len() == forms.len()
+                    && patterns
+                        .iter()
+                        .zip(forms)
+                        .all(|(pattern, form)| self.matches(pattern, form, bindings))
+            }
+        };
+        let before = &patterns[..ellipsis - 1];
+        let repeated = &patterns[ellipsis - 1];
+        let after = &patterns[ellipsis + 1..];
+        if forms.len() < before.len() + after.len() {
+            return false;
+        }
+        let (head, rest) = forms.split_at(before.len());
+        let (middle, tail) = rest.split_at(rest.len() - after.len());
+        if !self.matches_list(before, head, bindings) || !self.matches_list(after, tail, bindings) {
+            return false;
+        }
+
+        let mut sequence = Vec::with_capacity(middle.len());
+        for form in middle {
+            let mut matched = Bindings::new();
+            if !self.matches(repeated, form, &mut matched) {
+                return false;
+            }
+            sequence.push(matched);
+        }
+        for variable in self.variables(repeated) {
+            let values = sequence
+                .iter_mut()
+                .filter_map(|matched| matched.remove(&variable))
+                .collect();
+            bindings.insert(variable, Binding::Sequence(values));
+        }
+        true
+    }
+
+    /// Returns the pattern variables of the pattern.
+    fn variables(&self, pattern: &Item) -> Vec<String> {
+        match pattern {
+            Item::Name(name)
+                if base(name) != "_" && !self.is_literal(name) && !self.is_ellipsis(pattern) =>
+            {
+                vec![name.clone()]
+            }
+            Item::Cons(list) => list
+                .iter()
+                .flat_map(|pattern| self.variables(pattern))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn instantiate(
+        &self,
+        template: &Item,
+        bindings: &Bindings,
+        renamer: &mut Renamer,
+    ) -> VMOutput<Item> {
+        let list = match template {
+            Item::Name(name) => {
+                return match bindings.get(name) {
+                    Some(Binding::Single(item)) => Ok(item.clone()),
+                    Some(Binding::Sequence(_)) => Err(VMError::malformed(
+                        &self.name,
+                        &format!("'{}' must be followed by an ellipsis", name),
+                    )),
+                    None => Ok(Item::Name(renamer.rename(name))),
+                }
+            }
+            Item::Cons(list) => list,
+            template => return Ok(template.clone()),
+        };
+
+        let elements = list.elements();
+        let mut items = Vec::with_capacity(elements.len());
+        let mut i = 0;
+        while i < elements.len() {
+            let depth = elements[i + 1..]
+                .iter()
+                .take_while(|item| self.is_ellipsis(item))
+                .count();
+            if depth == 0 {
+                items.push(self.instantiate(&elements[i], bindings, renamer)?);
+            } else {
+                self.repeat(&elements[i], bindings, depth, renamer, &mut items)?;
+            }
+            i += depth + 1;
+        }
+        match list.nth_cdr(elements.len()) {
+            Some(Item::None) | None => Ok(gen_list(items)),
+            Some(tail) => {
+                let tail = self.instantiate(&tail, bindings, renamer)?;
+                Ok(items
+                    .into_iter()
+                    .rev()
+                    .fold(tail, |rest, item| Item::Cons(Cons::new(item, rest))))
+            }
+        }
+    }
+
+    /// Instantiates the template once for every value of the sequences of
+    /// its pattern variables, where `depth` is the number of ellipses after
+    /// the template.
+    fn repeat(
+        &self,
+        template: &Item,
+        bindings: &Bindings,
+        depth: usize,
+        renamer: &mut Renamer,
+        items: &mut Vec<Item>,
+    ) -> VMOutput {
+        let sequences: Vec<(&String, &Vec<Binding>)> = bindings
+            .iter()
+            .filter_map(|(name, binding)| match binding {
+                Binding::Sequence(values) if occurs(name, template) => Some((name, values)),
+                _ => None,
+            })
+            .collect();
+        let length = match sequences.first() {
+            Some((_, values)) => values.len(),
+            None => {
+                return Err(VMError::malformed(
+                    &self.name,
+                    &format!("no pattern variable of {} matched a sequence", template),
+                ))
+            }
+        };
+        if sequences.iter().any(|(_, values)| values.len() != length) {
+            return Err(VMError::malformed(
+                &self.name,
+                &format!("the sequences of {} have different lengths", template),
+            ));
+        }
+        for i in 0..length {
+            let mut bindings = bindings.clone();
+            for (name, values) in &sequences {
+                bindings.insert((*name).clone(), values[i].clone());
+            }
+            if depth == 1 {
+                items.push(self.instantiate(template, &bindings, renamer)?);
+            } else {
+                self.repeat(template, &bindings, depth - 1, renamer, items)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of the item as written in the source code, if it is a
+/// name.
+fn base_name(item: &Item) -> Option<&str> {
+    match item {
+        Item::Name(name) => Some(base(name)),
+        _ => None,
+    }
+}
+
+/// Returns the elements of the item, if it is a proper list.
+fn list_of(item: &Item) -> Option<Vec<Item>> {
+    match item {
+        Item::None => Some(Vec::new()),
+        Item::Cons(list) if list.is_null_terminated() => Some(list.elements().to_vec()),
+        _ => None,
+    }
+}
+
+/// Returns true if the name occurs in the template.
+fn occurs(name: &str, template: &Item) -> bool {
+    match template {
+        Item::Name(n) => n == name,
+        Item::Cons(list) => list.iter().any(|item| occurs(name, item)),
+        _ => false,
+    }
+}
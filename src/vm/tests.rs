@@ -0,0 +1,926 @@
+use super::super::datastructure::{Foreign, Number};
+use super::super::lexer::Position;
+use super::super::stdlib::error::{EvalError, EvalErrorCode};
+use super::super::{convert, lexer, parser};
+use super::*;
+
+fn run(machine: &mut Machine, program: &str) -> VMOutput<EnvItem> {
+    let mut symbols = lexer::lex(program).expect("The program could not be lexed");
+    let ast = parser::parse(&mut symbols).expect("The program could not be parsed");
+    let mut result = EnvItem::None;
+    for item in convert::convert(ast) {
+        let item = machine.expand(item)?;
+        result = machine.eval(item)?;
+    }
+    Ok(result)
+}
+
+fn eval(program: &str) -> VMOutput<EnvItem> {
+    run(&mut Machine::default(), program)
+}
+
+#[test]
+fn closure_captures_parameter() {
+    assert_eq!(
+        eval(
+            "(def make-adder (lambda (n) (lambda (x) (+ x n))))
+             (def add5 (make-adder 5))
+             (add5 10)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(15)))
+    );
+}
+
+#[test]
+fn closure_ignores_caller_bindings() {
+    assert_eq!(
+        eval(
+            "(def constantly (lambda (x) (lambda () x)))
+             (def one (constantly 1))
+             (def call-with (lambda (x) (one)))
+             (call-with 2)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(1)))
+    );
+}
+
+#[test]
+fn caller_bindings_are_not_visible() {
+    assert_matches!(
+        eval(
+            "(def get-y (lambda () y))
+             (def with-y (lambda (y) (get-y)))
+             (with-y 2)"
+        ),
+        Err(_)
+    );
+}
+
+#[test]
+fn failed_eval_does_not_leak_bindings() {
+    let mut machine = Machine::default();
+    assert_matches!(
+        run(&mut machine, "((lambda (leaked) (undefined)) 1)"),
+        Err(_)
+    );
+    assert_matches!(run(&mut machine, "leaked"), Err(_));
+}
+
+#[test]
+fn division_promotes_to_rational() {
+    assert_eq!(
+        eval("(/ 1 4)").unwrap(),
+        EnvItem::Data(Item::Number(Number::rational(1, 4)))
+    );
+    assert_eq!(
+        eval("(/ 8 4)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(2)))
+    );
+}
+
+#[test]
+fn mixed_arithmetic_promotes_to_real() {
+    assert_eq!(
+        eval("(+ 1 1/2 0.25)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Real(1.75)))
+    );
+    assert_eq!(
+        eval("(* 3/4 4)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+}
+
+#[test]
+fn numeric_comparisons_across_kinds() {
+    assert_eq!(
+        eval("(= 1 1.0)").unwrap(),
+        EnvItem::Data(Item::Boolean(true))
+    );
+    assert_eq!(
+        eval("(< 1/3 0.5)").unwrap(),
+        EnvItem::Data(Item::Boolean(true))
+    );
+    assert_eq!(
+        eval("(> 1/3 1/2)").unwrap(),
+        EnvItem::Data(Item::Boolean(false))
+    );
+}
+
+#[test]
+fn multiplication_promotes_to_big_integer() {
+    assert_eq!(
+        format!("{}", eval("(* 9999999999 9999999999)").unwrap()),
+        "99999999980000000001"
+    );
+}
+
+#[test]
+fn big_integers_are_demoted_when_they_fit() {
+    assert_eq!(
+        eval("(- 99999999999999999999 99999999999999999998)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(1)))
+    );
+}
+
+#[test]
+fn addition_overflow_is_promoted() {
+    assert_eq!(
+        format!("{}", eval("(+ 9223372036854775807 1)").unwrap()),
+        "9223372036854775808"
+    );
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    assert_matches!(
+        eval("(/ 1 0)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0013))
+    );
+    assert_matches!(
+        eval("(/ 1.5 0.0)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0013))
+    );
+}
+
+#[test]
+fn modulo_by_zero_is_an_error() {
+    assert_matches!(
+        eval("(% 1 0)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0013))
+    );
+}
+
+#[test]
+fn subtraction_without_arguments_is_an_error() {
+    assert_matches!(
+        eval("(-)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0006))
+    );
+}
+
+fn depth_env() -> EnvItem {
+    EnvItem::Function(
+        "depth".into(),
+        Rc::new(|machine: &mut Machine| {
+            Ok(EnvItem::Data(Item::Number(Number::Integer(
+                machine.stack.len() as i64,
+            ))))
+        }),
+        Parameters::Individual(Vec::new()),
+        None,
+    )
+}
+
+#[test]
+fn tail_calls_run_in_constant_space() {
+    let mut machine = Machine::default();
+    machine.define("depth", depth_env()).unwrap();
+    run(
+        &mut machine,
+        "(def count-down (lambda (n) (if (= n 0) (depth) (count-down (- n 1)))))",
+    )
+    .unwrap();
+
+    let shallow = run(&mut machine, "(count-down 1)").unwrap();
+    let deep = run(&mut machine, "(count-down 1000)").unwrap();
+    assert_eq!(shallow, deep);
+}
+
+#[test]
+fn tail_call_sees_caller_arguments() {
+    assert_eq!(
+        eval(
+            "(def sum (lambda (n acc) (if (= n 0) acc (sum (- n 1) (+ acc n)))))
+             (sum 10000 0)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(50005000)))
+    );
+}
+
+#[test]
+fn lambda_body_returns_last_expression() {
+    assert_eq!(
+        eval("((lambda (x) (+ x 1) (+ x 2)) 1)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+}
+
+#[test]
+fn stack_depth_limit() {
+    let mut machine = Machine::with_options(MachineOptions {
+        max_stack_depth: Some(50),
+        ..Default::default()
+    });
+    assert_matches!(
+        run(
+            &mut machine,
+            "(def grow (lambda (n) (+ 1 (grow n))))
+             (grow 1)"
+        )
+        .map_err(|e| e.kind()),
+        Err(VMErrorKind::StackOverflow)
+    );
+}
+
+#[test]
+fn instruction_limit() {
+    let mut machine = Machine::with_options(MachineOptions {
+        max_instructions: Some(10_000),
+        ..Default::default()
+    });
+    assert_matches!(
+        run(
+            &mut machine,
+            "(def forever (lambda (n) (forever n)))
+             (forever 1)"
+        )
+        .map_err(|e| e.kind()),
+        Err(VMErrorKind::InstructionLimit)
+    );
+}
+
+#[test]
+fn instruction_limit_is_per_eval() {
+    let mut machine = Machine::with_options(MachineOptions {
+        max_instructions: Some(100),
+        ..Default::default()
+    });
+    for _ in 0..10 {
+        assert_matches!(run(&mut machine, "(+ 1 2 3)"), Ok(_));
+    }
+}
+
+#[test]
+fn allocation_limit() {
+    let mut machine = Machine::with_options(MachineOptions {
+        max_allocations: Some(1_000),
+        ..Default::default()
+    });
+    assert_matches!(
+        run(
+            &mut machine,
+            "(def build (lambda (list) (build (cons 1 list))))
+             (build ())"
+        )
+        .map_err(|e| e.kind()),
+        Err(VMErrorKind::AllocationLimit)
+    );
+}
+
+#[test]
+fn allocation_limit_ignores_passed_values() {
+    let mut machine = Machine::with_options(MachineOptions {
+        max_allocations: Some(100),
+        ..Default::default()
+    });
+    let list = (0..50).map(|i| i.to_string()).collect::<Vec<_>>().join(" ");
+    let program = format!(
+        "(def pass (lambda (n list) (if (= n 0) list (pass (- n 1) list))))
+         (pass 20 '({}))",
+        list
+    );
+    assert_matches!(
+        run(&mut machine, &program),
+        Ok(EnvItem::Data(Item::Cons(_)))
+    );
+}
+
+#[test]
+fn interrupted_machine_stops() {
+    let mut machine = Machine::default();
+    run(&mut machine, "(def forever (lambda (n) (forever n)))").unwrap();
+
+    let handle = machine.interrupt_handle();
+    let interrupter = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        handle.interrupt();
+    });
+    assert_matches!(
+        run(&mut machine, "(forever 1)").map_err(|e| e.kind()),
+        Err(VMErrorKind::Interrupted)
+    );
+    interrupter.join().unwrap();
+
+    assert_matches!(run(&mut machine, "(+ 1 2)"), Ok(_));
+}
+
+#[test]
+fn error_reports_position_of_failing_expression() {
+    let error = eval("(+ 1 2)\n(def x\n  (+ 1 (car 5)))").unwrap_err();
+    assert_eq!(error.position(), Some(Position::at(3, 7)));
+}
+
+#[test]
+fn unbound_name_reports_position() {
+    let error = eval("(+ 1\n   (* 2 undefined))").unwrap_err();
+    assert_eq!(error.position(), Some(Position::at(2, 8)));
+}
+
+#[test]
+fn error_reports_lisp_call_stack() {
+    let error = eval(
+        "(def inner (lambda (x) (car x)))
+         (def outer (lambda (x) (+ 1 (inner x))))
+         (outer 5)",
+    )
+    .unwrap_err();
+    let names: Vec<&str> = error.trace().iter().map(|frame| frame.name()).collect();
+    assert_eq!(names, vec!["lambda", "lambda", "car"]);
+    assert_eq!(error.trace()[0].position(), Some(Position::at(3, 9)));
+}
+
+#[test]
+fn call_stack_skips_special_forms() {
+    let error = eval(
+        "(def f (lambda (x)
+           (if x (begin (and #t (car x))) 0)))
+         (f 5)",
+    )
+    .unwrap_err();
+    let frames: Vec<(&str, Option<Position>)> = error
+        .trace()
+        .iter()
+        .map(|frame| (frame.name(), frame.position()))
+        .collect();
+    assert_eq!(
+        frames,
+        vec![
+            ("lambda", Some(Position::at(3, 9))),
+            ("car", Some(Position::at(2, 32)))
+        ]
+    );
+}
+
+#[test]
+fn register_fn_converts_arguments() {
+    let mut machine = Machine::default();
+    machine
+        .register_fn("sum-list", |list: Vec<i64>, extra: Option<i64>| {
+            Ok(list.iter().sum::<i64>() + extra.unwrap_or(0))
+        })
+        .unwrap();
+    assert_eq!(
+        run(&mut machine, "(sum-list '(1 2 3) 4)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(10)))
+    );
+    assert_eq!(
+        run(&mut machine, "(sum-list () ())").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(0)))
+    );
+}
+
+#[test]
+fn register_fn_checks_types() {
+    let mut machine = Machine::default();
+    machine
+        .register_fn("shout", |s: String| Ok(s.to_uppercase()))
+        .unwrap();
+    assert_eq!(
+        run(&mut machine, "(shout \"hi\")").unwrap(),
+        EnvItem::Data(Item::String("HI".into()))
+    );
+    assert_matches!(
+        run(&mut machine, "(shout 1)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0012))
+    );
+}
+
+#[test]
+fn register_fn_accepts_integers_that_fit() {
+    let big = |i: i64| EnvItem::Data(Item::Number(Number::BigInteger(i.into())));
+    assert_eq!(i64::from_item(big(i64::MAX)).unwrap(), i64::MAX);
+    assert_eq!(i64::from_item(big(-3)).unwrap(), -3);
+    let mut machine = Machine::default();
+    machine
+        .register_fn("double", |i: i64| Ok(i.saturating_mul(2)))
+        .unwrap();
+    assert_matches!(
+        run(&mut machine, "(double 100000000000000000000)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0012))
+    );
+}
+
+#[test]
+fn register_fn_checks_arity() {
+    let mut machine = Machine::default();
+    machine.register_fn("unit", || Ok(())).unwrap();
+    assert_eq!(
+        run(&mut machine, "(unit)").unwrap(),
+        EnvItem::Data(Item::None)
+    );
+    assert_matches!(
+        run(&mut machine, "(unit 1)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0006))
+    );
+}
+
+#[test]
+fn register_fn_propagates_errors() {
+    let mut machine = Machine::default();
+    machine
+        .register_fn("fail", |message: String| -> Result<Item, _> {
+            Err(EvalError::new(EvalErrorCode::E0007, message))
+        })
+        .unwrap();
+    assert_matches!(
+        run(&mut machine, "(fail \"nope\")").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0007))
+    );
+}
+
+struct Counter {
+    count: std::cell::Cell<i64>,
+}
+
+#[test]
+fn foreign_values_round_trip() {
+    let mut machine = Machine::default();
+    let counter = Rc::new(Counter {
+        count: std::cell::Cell::new(0),
+    });
+    machine
+        .define(
+            "counter",
+            EnvItem::Data(Item::Foreign(Foreign::from_rc(counter.clone()))),
+        )
+        .unwrap();
+    machine
+        .register_fn("increment", |counter: Rc<Counter>| {
+            counter.count.set(counter.count.get() + 1);
+            Ok(counter)
+        })
+        .unwrap();
+
+    assert_eq!(
+        run(&mut machine, "(foreign? (increment (increment counter)))").unwrap(),
+        EnvItem::Data(Item::Boolean(true))
+    );
+    assert_eq!(counter.count.get(), 2);
+    assert_eq!(
+        run(&mut machine, "(= counter (increment counter))").unwrap(),
+        EnvItem::Data(Item::Boolean(true))
+    );
+}
+
+#[test]
+fn foreign_values_are_type_checked() {
+    let mut machine = Machine::default();
+    machine
+        .define("text", EnvItem::Data(Item::Foreign(Foreign::new("text"))))
+        .unwrap();
+    machine
+        .register_fn("count", |counter: Rc<Counter>| Ok(counter.count.get()))
+        .unwrap();
+    assert_matches!(
+        run(&mut machine, "(count text)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0012))
+    );
+}
+
+#[test]
+fn set_updates_nearest_binding() {
+    assert_eq!(
+        eval(
+            "(def x 1)
+             (def shadow (lambda (x) (set! x 10) x))
+             (+ (shadow 2) x)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(11)))
+    );
+}
+
+#[test]
+fn set_updates_captured_binding() {
+    assert_eq!(
+        eval(
+            "(def make-counter (lambda () (let ((n 0)) (lambda () (set! n (+ n 1)) n))))
+             (def counter (make-counter))
+             (counter)
+             (counter)
+             (counter)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+}
+
+#[test]
+fn set_unbound_name_is_an_error() {
+    assert_matches!(
+        eval("(set! undefined 1)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0002))
+    );
+}
+
+#[test]
+fn let_binds_in_parallel() {
+    assert_eq!(
+        eval("(def x 1) (let ((x 2) (y x)) (+ x y))").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+}
+
+#[test]
+fn let_star_binds_in_sequence() {
+    assert_eq!(
+        eval("(def x 1) (let* ((x 2) (y x)) (+ x y))").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(4)))
+    );
+}
+
+#[test]
+fn let_bindings_are_local() {
+    assert_matches!(eval("(let ((local 1)) local) local"), Err(_));
+}
+
+#[test]
+fn letrec_allows_mutual_recursion() {
+    assert_eq!(
+        eval(
+            "(letrec ((even (lambda (n) (if (= n 0) #t (odd (- n 1)))))
+                      (odd (lambda (n) (if (= n 0) #f (even (- n 1))))))
+               (even 1000))"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Boolean(true))
+    );
+}
+
+#[test]
+fn malformed_let_is_an_error() {
+    assert_matches!(
+        eval("(let (x 1) x)").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0014))
+    );
+}
+
+#[test]
+fn begin_evaluates_in_order() {
+    assert_eq!(
+        eval("(begin (def x 1) (def y (+ x 1)) (+ x y))").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+    assert_eq!(eval("(begin)").unwrap(), EnvItem::Data(Item::None));
+}
+
+#[test]
+fn and_or_short_circuit() {
+    assert_eq!(
+        eval("(and 1 #f undefined)").unwrap(),
+        EnvItem::Data(Item::Boolean(false))
+    );
+    assert_eq!(
+        eval("(or #f 2 undefined)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(2)))
+    );
+    assert_eq!(
+        eval("(and 1 2 3)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+    assert_eq!(eval("(and)").unwrap(), EnvItem::Data(Item::Boolean(true)));
+    assert_eq!(eval("(or)").unwrap(), EnvItem::Data(Item::Boolean(false)));
+}
+
+#[test]
+fn cond_picks_first_matching_clause() {
+    let source = "(def sign (lambda (n)
+                    (cond ((< n 0) 'negative)
+                          ((= n 0) 'zero)
+                          (else 'positive))))";
+    assert_eq!(
+        eval(&format!("{} (sign -5)", source)).unwrap(),
+        EnvItem::Data(Item::Name("negative".into()))
+    );
+    assert_eq!(
+        eval(&format!("{} (sign 0)", source)).unwrap(),
+        EnvItem::Data(Item::Name("zero".into()))
+    );
+    assert_eq!(
+        eval(&format!("{} (sign 5)", source)).unwrap(),
+        EnvItem::Data(Item::Name("positive".into()))
+    );
+    assert_eq!(
+        eval("(cond (#f 1) (7))").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(7)))
+    );
+    assert_eq!(eval("(cond (#f 1))").unwrap(), EnvItem::Data(Item::None));
+}
+
+#[test]
+fn cond_else_must_be_last() {
+    assert_matches!(
+        eval("(cond (else 1) (#t 2))").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0014))
+    );
+}
+
+#[test]
+fn when_and_unless_run_body_conditionally() {
+    assert_eq!(
+        eval("(def x 0) (when #t (set! x 1) (+ x 1))").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(2)))
+    );
+    assert_eq!(
+        eval("(when #f undefined)").unwrap(),
+        EnvItem::Data(Item::None)
+    );
+    assert_eq!(
+        eval("(unless #f 1 2)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(2)))
+    );
+    assert_eq!(
+        eval("(unless #t undefined)").unwrap(),
+        EnvItem::Data(Item::None)
+    );
+}
+
+#[test]
+fn control_forms_are_tail_calls() {
+    let mut machine = Machine::default();
+    machine.define("depth", depth_env()).unwrap();
+    run(
+        &mut machine,
+        "(def count-down (lambda (n)
+           (cond ((= n 0) (depth))
+                 (else (begin (when #f 1) (or #f (and #t (count-down (- n 1)))))))))",
+    )
+    .unwrap();
+
+    let shallow = run(&mut machine, "(count-down 1)").unwrap();
+    let deep = run(&mut machine, "(count-down 1000)").unwrap();
+    assert_eq!(shallow, deep);
+}
+
+#[test]
+fn if_without_else_is_empty_when_false() {
+    assert_eq!(
+        eval("(if #t 1)").unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(1)))
+    );
+    assert_eq!(
+        eval("(if #f undefined)").unwrap(),
+        EnvItem::Data(Item::None)
+    );
+}
+
+#[test]
+fn malformed_special_forms_are_located_errors() {
+    for source in &[
+        "(if)",
+        "(if #t)",
+        "(if #t 1 2 3)",
+        "(quote)",
+        "(quote a b)",
+        "(def x)",
+        "(lambda)",
+        "(lambda (1) 1)",
+        "(eval)",
+        "(set! x)",
+    ] {
+        let error = eval(&format!("(car\n  {})", source)).unwrap_err();
+        assert_eq!(error.code(), Some(EvalErrorCode::E0014), "{}", source);
+        assert_eq!(error.position(), Some(Position::at(2, 2)), "{}", source);
+    }
+}
+
+#[test]
+fn quasiquote_fills_in_unquotes() {
+    let program = |template: &str| {
+        eval(&format!(
+            "(def x 1) (def xs (cons 2 (cons 3 ()))) {}",
+            template
+        ))
+        .map(Item::from)
+        .unwrap()
+        .to_string()
+    };
+    assert_eq!(program("`(a ,x b)"), "(a 1 b)");
+    assert_eq!(program("`(a ,@xs b)"), "(a 2 3 b)");
+    assert_eq!(program("`(a ,@() b)"), "(a b)");
+    assert_eq!(program("`((,x) ,(+ x 1))"), "((1) 2)");
+    assert_eq!(
+        program("`(a `(b ,(c ,x)))"),
+        "(a (quasiquote (b (unquote (c 1)))))"
+    );
+    assert_eq!(program("`x"), "x");
+}
+
+#[test]
+fn unquote_outside_quasiquote_is_an_error() {
+    assert_matches!(
+        eval("(def x 1) ,x").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0014))
+    );
+    assert_matches!(
+        eval("(def x 1) `,@x").map_err(|e| e.code()),
+        Err(Some(EvalErrorCode::E0014))
+    );
+}
+
+#[test]
+fn defmacro_expands_before_evaluation() {
+    assert_eq!(
+        eval(
+            "(defmacro swap! (a b) `(let ((tmp ,a)) (set! ,a ,b) (set! ,b tmp)))
+             (def x 1)
+             (def y 2)
+             (swap! x y)
+             (cons x y)"
+        )
+        .map(Item::from)
+        .unwrap()
+        .to_string(),
+        "(2 . 1)"
+    );
+}
+
+#[test]
+fn defmacro_receives_unevaluated_arguments() {
+    assert_eq!(
+        eval(
+            "(defmacro my-unless form `(if ,(car form) () (begin ,@(cdr form))))
+             (my-unless #t undefined)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::None)
+    );
+    assert_eq!(
+        eval(
+            "(defmacro my-unless form `(if ,(car form) () (begin ,@(cdr form))))
+             (my-unless #f 1 2)"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(2)))
+    );
+}
+
+#[test]
+fn syntax_rules_does_not_capture_user_names() {
+    assert_eq!(
+        eval(
+            "(define-syntax swap!
+               (syntax-rules ()
+                 ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))
+             (def tmp 1)
+             (def other 2)
+             (swap! tmp other)
+             (cons tmp other)"
+        )
+        .map(Item::from)
+        .unwrap()
+        .to_string(),
+        "(2 . 1)"
+    );
+}
+
+#[test]
+fn syntax_rules_free_names_resolve_where_used() {
+    assert_eq!(
+        eval(
+            "(define-syntax inc (syntax-rules () ((_ x) (+ x 1))))
+             (cons (inc 5) (let ((+ -)) (inc 5)))"
+        )
+        .map(Item::from)
+        .unwrap()
+        .to_string(),
+        "(6 . 4)"
+    );
+}
+
+#[test]
+fn syntax_rules_matches_ellipsis_recursively() {
+    let my_or = "(define-syntax my-or
+                   (syntax-rules ()
+                     ((_) #f)
+                     ((_ e) e)
+                     ((_ e rest ...) (let ((t e)) (if t t (my-or rest ...))))))";
+    assert_eq!(
+        eval(&format!("{} (def t 5) (my-or #f t)", my_or)).unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(5)))
+    );
+    assert_eq!(
+        eval(&format!("{} (my-or)", my_or)).unwrap(),
+        EnvItem::Data(Item::Boolean(false))
+    );
+}
+
+#[test]
+fn syntax_rules_matches_literals() {
+    assert_eq!(
+        eval(
+            "(define-syntax my-cond
+               (syntax-rules (else)
+                 ((_ (else e)) e)
+                 ((_ (c e) clause ...) (if c e (my-cond clause ...)))))
+             (my-cond (#f 1) ((= 1 2) 2) (else 3))"
+        )
+        .unwrap(),
+        EnvItem::Data(Item::Number(Number::Integer(3)))
+    );
+}
+
+#[test]
+fn syntax_rules_expands_nested_ellipses() {
+    assert_eq!(
+        eval(
+            "(define-syntax rotate
+               (syntax-rules ()
+                 ((_ (a b ...) ...) '((b ... a) ...))))
+             (rotate (1 2 3) (4 5) (6))"
+        )
+        .map(Item::from)
+        .unwrap()
+        .to_string(),
+        "((2 3 1) (5 4) (6))"
+    );
+}
+
+#[test]
+fn syntax_rules_without_matching_rule_is_an_error() {
+    let error = eval(
+        "(define-syntax pair (syntax-rules () ((_ a b) (cons a b))))
+         (pair 1)",
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), Some(EvalErrorCode::E0014));
+    assert_eq!(error.position(), Some(Position::at(2, 9)));
+}
+
+#[test]
+fn macroexpand_expands_nested_macro_uses() {
+    assert_eq!(
+        eval(
+            "(defmacro my-unless (test body) `(if ,test () ,body))
+             (define-syntax my-when (syntax-rules () ((_ test body) (if test body ()))))
+             (macroexpand '(my-unless done (my-when ready (my-unless x y))))"
+        )
+        .map(Item::from)
+        .unwrap()
+        .to_string(),
+        "(if done () (if ready (if x () y) ()))"
+    );
+}
+
+#[test]
+fn macroexpand_1_expands_once() {
+    let mut machine = Machine::default();
+    run(
+        &mut machine,
+        "(defmacro my-unless (test body) `(if ,test () ,body))
+         (defmacro never (body) `(my-unless #t ,body))",
+    )
+    .unwrap();
+    assert_eq!(
+        Item::from(run(&mut machine, "(macroexpand-1 '(never (never 1)))").unwrap()).to_string(),
+        "(my-unless #t (never 1))"
+    );
+    assert_eq!(
+        Item::from(run(&mut machine, "(macroexpand-1 '(+ 1 2))").unwrap()).to_string(),
+        "(+ 1 2)"
+    );
+}
+
+#[test]
+fn expand_defines_macros_for_later_forms() {
+    let mut machine = Machine::default();
+    let mut symbols = lexer::lex(
+        "(begin
+           (defmacro inc! (name) `(set! ,name (+ ,name 1)))
+           (lambda (x) (inc! x) x))",
+    )
+    .unwrap();
+    let ast = parser::parse(&mut symbols).unwrap();
+    let item = convert::convert(ast).remove(0);
+    let expanded = machine.expand(item).unwrap().to_string();
+    assert!(expanded.ends_with("(lambda (x) (set! x (+ x 1)) x))"));
+}
+
+#[test]
+fn expanded_code_reads_back() {
+    let mut machine = Machine::default();
+    run(
+        &mut machine,
+        "(define-syntax swap!
+           (syntax-rules ()
+             ((_ a b) (let ((tmp a)) (set! a b) (set! b tmp)))))
+         (def p 1)
+         (def q 2)",
+    )
+    .unwrap();
+    let expanded = Item::from(run(&mut machine, "(macroexpand '(swap! p q))").unwrap());
+    let printed = expanded.to_string();
+    assert!(printed.starts_with("(let ((|tmp,"));
+
+    let mut symbols = lexer::lex(&printed).unwrap();
+    let ast = parser::parse(&mut symbols).unwrap();
+    assert_eq!(convert::convert(ast), vec![expanded]);
+    run(&mut machine, &printed).unwrap();
+    assert_eq!(
+        Item::from(run(&mut machine, "(cons p q)").unwrap()).to_string(),
+        "(2 . 1)"
+    );
+}